@@ -0,0 +1,62 @@
+use std::fmt::Display;
+
+/// A single day's puzzle. The input is parsed once and both halves are solved against it.
+pub trait Solution {
+    type Input<'a>;
+    type Answer: Display;
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    fn parse(input: &str) -> Result<Self::Input<'_>, Self::Error>;
+
+    fn part_one(input: &Self::Input<'_>) -> Result<Self::Answer, Self::Error>;
+
+    /// Days that only have the first half solved leave this as `None`.
+    fn part_two(_input: &Self::Input<'_>) -> Option<Result<Self::Answer, Self::Error>> {
+        None
+    }
+}
+
+/// Where a token sits in the puzzle input. Both the line and the column are 1-based.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+
+    /// Locates `token`, which must be a slice of `line`, on the line with index `line_index`.
+    pub fn of(line_index: usize, line: &str, token: &str) -> Self {
+        let offset = (token.as_ptr() as usize).wrapping_sub(line.as_ptr() as usize);
+        let column = match line
+            .get(..offset)
+            .filter(|_| offset + token.len() <= line.len())
+        {
+            Some(prefix) => prefix.chars().count() + 1,
+            None => 1,
+        };
+        Self::new(line_index + 1, column)
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_of_token() {
+        let line = "Game 12: 3 blue";
+        let token = &line[5..7];
+        assert_eq!(Location::of(3, line, token), Location::new(4, 6));
+        assert_eq!(Location::new(4, 6).to_string(), "line 4, column 6");
+    }
+}
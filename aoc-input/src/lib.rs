@@ -0,0 +1,175 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_BASE_URL: &str = "https://adventofcode.com";
+const USER_AGENT: &str = "github.com/clundin55/advent-of-code-2023";
+// The site asks automated tools to throttle themselves, so never hit it more than once every few
+// seconds even across separate runs.
+const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(5);
+const LAST_REQUEST_FILE: &str = ".last-request";
+
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("unable to find a cache directory for puzzle inputs")]
+    MissingCacheDir,
+    #[error("the session token was rejected")]
+    Unauthorized,
+    #[error("the input for {year} day {day} isn't available")]
+    NotFound { year: u16, day: u8 },
+    #[error("the server is rate limiting requests")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("the server responded with status {0}")]
+    Status(u16),
+    #[error("request failed: {0}")]
+    Transport(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Where downloaded inputs live unless a client is given its own directory.
+pub fn default_cache_dir() -> Result<PathBuf, FetchError> {
+    Ok(dirs::cache_dir()
+        .ok_or(FetchError::MissingCacheDir)?
+        .join("advent-of-code"))
+}
+
+/// Downloads puzzle inputs with a session token and caches them on disk by year and day.
+pub struct Client {
+    session: String,
+    base_url: String,
+    cache_dir: PathBuf,
+    min_interval: Duration,
+    agent: ureq::Agent,
+}
+
+// Written by hand so the session token never ends up in logs or error reports.
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("session", &"<redacted>")
+            .field("base_url", &self.base_url)
+            .field("cache_dir", &self.cache_dir)
+            .field("min_interval", &self.min_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Client {
+    pub fn new(session: impl Into<String>) -> Result<Self, FetchError> {
+        Ok(Self::with_cache_dir(session, default_cache_dir()?))
+    }
+
+    pub fn with_cache_dir(session: impl Into<String>, cache_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            session: session.into(),
+            base_url: DEFAULT_BASE_URL.to_owned(),
+            cache_dir: cache_dir.into(),
+            min_interval: DEFAULT_MIN_INTERVAL,
+            agent: ureq::AgentBuilder::new().user_agent(USER_AGENT).build(),
+        }
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+
+    pub fn cache_path(&self, year: u16, day: u8) -> PathBuf {
+        self.cache_dir
+            .join(year.to_string())
+            .join(format!("{day}.txt"))
+    }
+
+    pub fn cached(&self, year: u16, day: u8) -> Result<Option<String>, FetchError> {
+        match fs::read_to_string(self.cache_path(year, day)) {
+            Ok(input) => Ok(Some(input)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the cached input when there is one, otherwise downloads and caches it.
+    pub fn input(&self, year: u16, day: u8) -> Result<String, FetchError> {
+        if let Some(input) = self.cached(year, day)? {
+            return Ok(input);
+        }
+
+        let input = self.download(year, day)?;
+        let path = self.cache_path(year, day);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Written next to the real file and moved into place, so an interrupted run never leaves
+        // half an input behind for `cached` to find.
+        let partial = path.with_extension(format!("txt.{}.partial", std::process::id()));
+        fs::write(&partial, &input)?;
+        if let Err(e) = fs::rename(&partial, &path) {
+            let _ = fs::remove_file(&partial);
+            return Err(e.into());
+        }
+        Ok(input)
+    }
+
+    fn download(&self, year: u16, day: u8) -> Result<String, FetchError> {
+        self.throttle()?;
+
+        let url = format!(
+            "{}/{year}/day/{day}/input",
+            self.base_url.trim_end_matches('/')
+        );
+        let response = self
+            .agent
+            .get(&url)
+            .set("Cookie", &format!("session={}", self.session))
+            .call();
+
+        match response {
+            Ok(response) => Ok(response.into_string()?),
+            Err(ureq::Error::Status(code, response)) => Err(match code {
+                400 | 401 | 403 => FetchError::Unauthorized,
+                404 => FetchError::NotFound { year, day },
+                429 => FetchError::RateLimited {
+                    retry_after: response
+                        .header("Retry-After")
+                        .and_then(|seconds| seconds.parse().ok())
+                        .map(Duration::from_secs),
+                },
+                code => FetchError::Status(code),
+            }),
+            Err(ureq::Error::Transport(transport)) => {
+                Err(FetchError::Transport(transport.to_string()))
+            }
+        }
+    }
+
+    // The time of the last request lives next to the cached inputs so separate runs share it.
+    fn throttle(&self) -> Result<(), FetchError> {
+        fs::create_dir_all(&self.cache_dir)?;
+        let stamp = self.cache_dir.join(LAST_REQUEST_FILE);
+
+        let last_request = fs::read_to_string(&stamp)
+            .ok()
+            .and_then(|millis| millis.trim().parse::<u64>().ok())
+            .map(|millis| UNIX_EPOCH + Duration::from_millis(millis));
+        if let Some(elapsed) = last_request.and_then(|last| last.elapsed().ok()) {
+            if elapsed < self.min_interval {
+                thread::sleep(self.min_interval - elapsed);
+            }
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_millis());
+        fs::write(stamp, now.to_string())?;
+        Ok(())
+    }
+}
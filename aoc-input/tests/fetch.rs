@@ -0,0 +1,148 @@
+use aoc_input::{Client, FetchError};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+struct Request {
+    path: String,
+    cookie: String,
+}
+
+// Stands in for the real site: answers each connection with the next canned response and
+// remembers what was asked for.
+struct StandIn {
+    url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl StandIn {
+    fn serve(responses: Vec<(u16, &'static str, &'static str)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = Arc::clone(&requests);
+        thread::spawn(move || {
+            for (status, headers, body) in responses {
+                let Ok((mut stream, _)) = listener.accept() else {
+                    return;
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = Request::default();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    if let Some(path) = line.strip_prefix("GET ") {
+                        request.path = path.split(' ').next().unwrap().to_owned();
+                    }
+                    if let Some(cookie) = line.strip_prefix("Cookie: ") {
+                        request.cookie = cookie.trim().to_owned();
+                    }
+                    line.clear();
+                }
+                recorded.lock().unwrap().push(request);
+
+                let response = format!(
+                    "HTTP/1.1 {status} Stand-In\r\nContent-Length: {}\r\nConnection: close\r\n{headers}\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    fn requests(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+}
+
+fn client(server: &StandIn, cache: &tempfile::TempDir) -> Client {
+    Client::with_cache_dir("token", cache.path())
+        .base_url(&server.url)
+        .min_interval(Duration::ZERO)
+}
+
+#[test]
+fn fetches_and_caches_input() {
+    let server = StandIn::serve(vec![(200, "", "0 3 6 9 12 15\n")]);
+    let cache = tempfile::tempdir().unwrap();
+    let client = client(&server, &cache);
+
+    assert_eq!(client.cached(2023, 9).unwrap(), None);
+    assert_eq!(client.input(2023, 9).unwrap(), "0 3 6 9 12 15\n");
+    assert_eq!(client.input(2023, 9).unwrap(), "0 3 6 9 12 15\n");
+    assert_eq!(server.requests(), 1);
+
+    let requests = server.requests.lock().unwrap();
+    assert_eq!(requests[0].path, "/2023/day/9/input");
+    assert_eq!(requests[0].cookie, "session=token");
+    assert_eq!(
+        std::fs::read_to_string(cache.path().join("2023").join("9.txt")).unwrap(),
+        "0 3 6 9 12 15\n"
+    );
+}
+
+#[test]
+fn reports_server_errors() {
+    let server = StandIn::serve(vec![
+        (
+            400,
+            "",
+            "Puzzle inputs differ by user.  Please log in to get your puzzle input.\n",
+        ),
+        (
+            404,
+            "",
+            "Please don't repeatedly request this endpoint before it unlocks!\n",
+        ),
+        (429, "Retry-After: 30\r\n", ""),
+        (500, "", ""),
+    ]);
+    let cache = tempfile::tempdir().unwrap();
+    let client = client(&server, &cache);
+
+    assert!(matches!(
+        client.input(2023, 1),
+        Err(FetchError::Unauthorized)
+    ));
+    assert!(matches!(
+        client.input(2023, 25),
+        Err(FetchError::NotFound {
+            year: 2023,
+            day: 25
+        })
+    ));
+    assert!(matches!(
+        client.input(2023, 2),
+        Err(FetchError::RateLimited { retry_after: Some(retry_after) })
+            if retry_after == Duration::from_secs(30)
+    ));
+    assert!(matches!(
+        client.input(2023, 3),
+        Err(FetchError::Status(500))
+    ));
+
+    assert_eq!(client.cached(2023, 1).unwrap(), None);
+    assert_eq!(server.requests(), 4);
+}
+
+#[test]
+fn throttles_requests() {
+    let server = StandIn::serve(vec![(200, "", "LR\n"), (200, "", "RL\n")]);
+    let cache = tempfile::tempdir().unwrap();
+    let min_interval = Duration::from_millis(300);
+
+    let start = Instant::now();
+    let first = client(&server, &cache).min_interval(min_interval);
+    assert_eq!(first.input(2023, 8).unwrap(), "LR\n");
+    // A fresh client still sees the previous request through the cache directory.
+    let second = client(&server, &cache).min_interval(min_interval);
+    assert_eq!(second.input(2022, 8).unwrap(), "RL\n");
+
+    assert!(start.elapsed() >= min_interval);
+    assert_eq!(server.requests(), 2);
+}
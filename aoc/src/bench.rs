@@ -0,0 +1,142 @@
+use serde::Serialize;
+use std::fmt::{Display, Write};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Parse,
+    PartOne,
+    PartTwo,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let phase = match self {
+            Phase::Parse => "parse",
+            Phase::PartOne => "part one",
+            Phase::PartTwo => "part two",
+        };
+        f.pad(phase)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Summary {
+    pub samples: usize,
+    pub min: Duration,
+    pub median: Duration,
+    pub max: Duration,
+}
+
+/// Times `samples` calls of `f`, returning the last value it produced along with the timings.
+pub fn measure<T, E>(
+    samples: usize,
+    mut f: impl FnMut() -> Result<T, E>,
+) -> Result<(T, Summary), E> {
+    let mut timings = Vec::with_capacity(samples);
+    let mut value = None;
+    for _ in 0..samples.max(1) {
+        let start = Instant::now();
+        let result = black_box(f()?);
+        timings.push(start.elapsed());
+        value = Some(result);
+    }
+    timings.sort();
+
+    let summary = Summary {
+        samples: timings.len(),
+        min: timings[0],
+        median: timings[timings.len() / 2],
+        max: timings[timings.len() - 1],
+    };
+    Ok((value.expect("At least one sample is always taken"), summary))
+}
+
+#[derive(Debug, Serialize)]
+pub struct Measurement {
+    pub day: u8,
+    pub phase: Phase,
+    pub samples: usize,
+    pub min_ns: u128,
+    pub median_ns: u128,
+    pub max_ns: u128,
+}
+
+impl Measurement {
+    pub fn new(day: u8, phase: Phase, summary: Summary) -> Self {
+        Self {
+            day,
+            phase,
+            samples: summary.samples,
+            min_ns: summary.min.as_nanos(),
+            median_ns: summary.median.as_nanos(),
+            max_ns: summary.max.as_nanos(),
+        }
+    }
+}
+
+fn duration(nanos: u128) -> String {
+    let nanos = u64::try_from(nanos).unwrap_or(u64::MAX);
+    format!("{:.2?}", Duration::from_nanos(nanos))
+}
+
+pub fn table(measurements: &[Measurement]) -> String {
+    let mut table = format!(
+        "{:>3}  {:<8}  {:>10}  {:>10}  {:>10}\n",
+        "day", "phase", "min", "median", "max"
+    );
+    for measurement in measurements {
+        let _ = writeln!(
+            table,
+            "{:>3}  {:<8}  {:>10}  {:>10}  {:>10}",
+            measurement.day,
+            measurement.phase,
+            duration(measurement.min_ns),
+            duration(measurement.median_ns),
+            duration(measurement.max_ns),
+        );
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measure_orders_samples() {
+        let mut calls = 0;
+        let (last, summary) = measure(5, || {
+            calls += 1;
+            Ok::<_, ()>(calls)
+        })
+        .unwrap();
+        assert_eq!(last, 5);
+        assert_eq!(summary.samples, 5);
+        assert!(summary.min <= summary.median && summary.median <= summary.max);
+
+        assert_eq!(measure(3, || Err::<(), _>("bad input")), Err("bad input"));
+    }
+
+    #[test]
+    fn table_and_json() {
+        let summary = Summary {
+            samples: 3,
+            min: Duration::from_micros(12),
+            median: Duration::from_micros(15),
+            max: Duration::from_millis(2),
+        };
+        let measurements = vec![Measurement::new(9, Phase::PartTwo, summary)];
+
+        assert_eq!(
+            table(&measurements),
+            "day  phase            min      median         max\n  9  part two     12.00µs     15.00µs      2.00ms\n"
+        );
+        assert_eq!(
+            serde_json::to_string(&measurements).unwrap(),
+            r#"[{"day":9,"phase":"part_two","samples":3,"min_ns":12000,"median_ns":15000,"max_ns":2000000}]"#
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use crate::bench::{self, Phase, Summary};
+use anyhow::Result;
+use aoc_core::Solution;
+use std::marker::PhantomData;
+
+/// Type-erased access to a day's `Solution` so the runner can pick one at runtime.
+pub trait Day {
+    fn solve(&self, input: &str, part: u8) -> Result<Option<String>>;
+
+    fn bench(&self, input: &str, samples: usize) -> Result<Vec<(Phase, Summary)>>;
+}
+
+struct Entry<S>(PhantomData<fn() -> S>);
+
+impl<S> Entry<S> {
+    const DAY: Self = Entry(PhantomData);
+}
+
+impl<S: Solution> Day for Entry<S> {
+    fn solve(&self, input: &str, part: u8) -> Result<Option<String>> {
+        let parsed = S::parse(input)?;
+        let answer = match part {
+            1 => Some(S::part_one(&parsed)),
+            _ => S::part_two(&parsed),
+        };
+        Ok(answer.transpose()?.map(|answer| answer.to_string()))
+    }
+
+    fn bench(&self, input: &str, samples: usize) -> Result<Vec<(Phase, Summary)>> {
+        let (parsed, parse) = bench::measure(samples, || S::parse(input))?;
+        let (_, part_one) = bench::measure(samples, || S::part_one(&parsed))?;
+        let mut summaries = vec![(Phase::Parse, parse), (Phase::PartOne, part_one)];
+
+        let (answer, part_two) = bench::measure(samples, || S::part_two(&parsed).transpose())?;
+        if answer.is_some() {
+            summaries.push((Phase::PartTwo, part_two));
+        }
+        Ok(summaries)
+    }
+}
+
+pub const SOLVED: std::ops::RangeInclusive<u8> = 1..=9;
+
+pub fn lookup(day: u8) -> Option<&'static dyn Day> {
+    let day: &'static dyn Day = match day {
+        1 => &Entry::<day1::Day1>::DAY,
+        2 => &Entry::<day2::Day2>::DAY,
+        3 => &Entry::<day3::Day3>::DAY,
+        4 => &Entry::<day4::Day4>::DAY,
+        5 => &Entry::<day5::Day5>::DAY,
+        6 => &Entry::<day6::Day6>::DAY,
+        7 => &Entry::<day7::Day7>::DAY,
+        8 => &Entry::<day8::Day8>::DAY,
+        9 => &Entry::<day9::Day9>::DAY,
+        _ => return None,
+    };
+    Some(day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY7_SAMPLE: &str = include_str!("../../day7/src/sample.txt");
+    const DAY9_SAMPLE: &str = include_str!("../../day9/src/sample.txt");
+
+    #[test]
+    fn every_solved_day_is_registered() {
+        assert!(SOLVED.clone().all(|day| lookup(day).is_some()));
+        assert!(lookup(*SOLVED.end() + 1).is_none());
+    }
+
+    #[test]
+    fn bench_phases() {
+        let phases = |day: u8, input: &str| -> Vec<Phase> {
+            let summaries = lookup(day).unwrap().bench(input, 2).unwrap();
+            summaries.into_iter().map(|(phase, _)| phase).collect()
+        };
+        assert_eq!(
+            phases(9, DAY9_SAMPLE),
+            vec![Phase::Parse, Phase::PartOne, Phase::PartTwo]
+        );
+        assert_eq!(
+            phases(7, DAY7_SAMPLE),
+            vec![Phase::Parse, Phase::PartOne, Phase::PartTwo]
+        );
+    }
+
+    // A day that's only halfway done still registers, it just has nothing to say for part two.
+    struct FirstHalf;
+
+    impl Solution for FirstHalf {
+        type Input<'a> = &'a str;
+        type Answer = usize;
+        type Error = std::fmt::Error;
+
+        fn parse(input: &str) -> Result<Self::Input<'_>, Self::Error> {
+            Ok(input)
+        }
+
+        fn part_one(input: &Self::Input<'_>) -> Result<Self::Answer, Self::Error> {
+            Ok(input.len())
+        }
+    }
+
+    #[test]
+    fn unsolved_part_two() {
+        let day = &Entry::<FirstHalf>::DAY;
+        assert_eq!(day.solve("abc", 1).unwrap(), Some("3".to_owned()));
+        assert_eq!(day.solve("abc", 2).unwrap(), None);
+        let phases: Vec<Phase> = day
+            .bench("abc", 2)
+            .unwrap()
+            .into_iter()
+            .map(|(phase, _)| phase)
+            .collect();
+        assert_eq!(phases, [Phase::Parse, Phase::PartOne]);
+    }
+}
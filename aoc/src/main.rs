@@ -0,0 +1,206 @@
+use anyhow::{bail, Context, Result};
+use bench::Measurement;
+use clap::{Parser, Subcommand};
+use std::io::Read;
+use std::path::PathBuf;
+
+mod bench;
+mod days;
+
+const YEAR: u16 = 2023;
+
+#[derive(Debug, Parser)]
+#[command(name = "aoc", about = "Advent of Code 2023 solutions")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Solve one part of a day's puzzle and print the answer.
+    Run {
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=25))]
+        day: u8,
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=2))]
+        part: u8,
+        /// File containing the puzzle input. Reads stdin when missing or `-`.
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Download the puzzle input (or reuse the cached copy) instead of reading it.
+        #[arg(long, conflicts_with = "input")]
+        fetch: bool,
+    },
+    /// Print a day's puzzle input, downloading it into the local cache if needed.
+    Fetch {
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=25))]
+        day: u8,
+        #[arg(long, default_value_t = YEAR)]
+        year: u16,
+    },
+    /// Time the parse and solve phases of every solved day.
+    Bench {
+        /// Only benchmark this day.
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=25))]
+        day: Option<u8>,
+        /// File containing the input for `--day`.
+        #[arg(long, requires = "day")]
+        input: Option<PathBuf>,
+        /// Directory holding one `<day>.txt` input per day. Defaults to the download cache.
+        #[arg(long, conflicts_with = "input")]
+        inputs: Option<PathBuf>,
+        /// How many times each phase is timed.
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+        samples: u64,
+        /// Print the measurements as JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+fn run(day: u8, part: u8, input: &str) -> Result<String> {
+    let Some(solution) = days::lookup(day) else {
+        bail!("Day {day} hasn't been solved yet");
+    };
+    let answer = solution
+        .solve(input, part)
+        .with_context(|| format!("Unable to solve day {day}"))?;
+    answer.with_context(|| format!("Day {day} part {part} hasn't been solved yet"))
+}
+
+fn bench(day: u8, input: &str, samples: usize) -> Result<Vec<Measurement>> {
+    let Some(solution) = days::lookup(day) else {
+        bail!("Day {day} hasn't been solved yet");
+    };
+    let summaries = solution
+        .bench(input, samples)
+        .with_context(|| format!("Unable to solve day {day}"))?;
+    Ok(summaries
+        .into_iter()
+        .map(|(phase, summary)| Measurement::new(day, phase, summary))
+        .collect())
+}
+
+fn bench_inputs(
+    day: Option<u8>,
+    input: Option<PathBuf>,
+    inputs: Option<PathBuf>,
+) -> Result<Vec<(u8, String)>> {
+    if let (Some(day), Some(input)) = (day, input) {
+        return Ok(vec![(day, read_input(Some(input))?)]);
+    }
+
+    let inputs = match inputs {
+        Some(inputs) => inputs,
+        None => aoc_input::default_cache_dir()?.join(YEAR.to_string()),
+    };
+    let read = |day: u8| -> Result<String> {
+        let path = inputs.join(format!("{day}.txt"));
+        std::fs::read_to_string(&path).with_context(|| format!("Unable to read {}", path.display()))
+    };
+
+    if let Some(day) = day {
+        return Ok(vec![(day, read(day)?)]);
+    }
+    let mut found = Vec::new();
+    for day in days::SOLVED {
+        match read(day) {
+            Ok(input) => found.push((day, input)),
+            Err(e) => eprintln!("Skipping day {day}: {e:#}"),
+        }
+    }
+    Ok(found)
+}
+
+fn read_input(path: Option<PathBuf>) -> Result<String> {
+    match path {
+        Some(path) if path.as_os_str() != "-" => std::fs::read_to_string(&path)
+            .with_context(|| format!("Unable to read {}", path.display())),
+        _ => {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .context("Unable to read stdin")?;
+            Ok(input)
+        }
+    }
+}
+
+fn fetch_input(year: u16, day: u8) -> Result<String> {
+    let session = std::env::var("AOC_SESSION")
+        .context("Set AOC_SESSION to your adventofcode.com session cookie")?;
+    let client = aoc_input::Client::new(session)?;
+    client
+        .input(year, day)
+        .with_context(|| format!("Unable to fetch the input for {year} day {day}"))
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    match cli.command {
+        Command::Run {
+            day,
+            part,
+            input,
+            fetch,
+        } => {
+            let input = if fetch {
+                fetch_input(YEAR, day)?
+            } else {
+                read_input(input)?
+            };
+            println!("{}", run(day, part, &input)?);
+        }
+        Command::Fetch { day, year } => {
+            print!("{}", fetch_input(year, day)?);
+        }
+        Command::Bench {
+            day,
+            input,
+            inputs,
+            samples,
+            json,
+        } => {
+            let samples = usize::try_from(samples)?;
+            let mut measurements = Vec::new();
+            for (day, input) in bench_inputs(day, input, inputs)? {
+                measurements.extend(bench(day, &input, samples)?);
+            }
+            if json {
+                println!("{}", serde_json::to_string_pretty(&measurements)?);
+            } else {
+                print!("{}", bench::table(&measurements));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY5_SAMPLE: &str = include_str!("../../day5/src/sample.txt");
+    const DAY7_SAMPLE: &str = include_str!("../../day7/src/sample.txt");
+
+    #[test]
+    fn run_sample() {
+        assert_eq!(run(5, 1, DAY5_SAMPLE).unwrap(), "35");
+        assert_eq!(run(5, 2, DAY5_SAMPLE).unwrap(), "46");
+        assert_eq!(run(7, 2, DAY7_SAMPLE).unwrap(), "5905");
+    }
+
+    #[test]
+    fn run_unsolved() {
+        assert!(run(25, 1, "").is_err());
+    }
+
+    #[test]
+    fn run_malformed() {
+        let error = run(7, 1, "32T3K 765\nT55X5 684").unwrap_err();
+        assert_eq!(
+            format!("{error:#}"),
+            "Unable to solve day 7: line 2, column 4: unexpected card `X`"
+        );
+    }
+}
@@ -1,87 +1,389 @@
-pub fn trebuchet_launch(calibrations: &str) -> u32 {
+use aoc_core::{Location, Solution};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("{location}: no calibration digit in `{token}`")]
+    MissingDigit { location: Location, token: String },
+}
+
+impl ParseError {
+    fn missing_digit(line_index: usize, line: &str) -> Self {
+        ParseError::MissingDigit {
+            location: Location::of(line_index, line, line),
+            token: line.to_owned(),
+        }
+    }
+}
+
+/// Why a spelling can't be added to a vocabulary.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum VocabularyError {
+    #[error("`{word}` can't spell {digit}, only the digits 1 to 9")]
+    InvalidDigit { word: String, digit: u32 },
+    #[error("an empty word can't spell {digit}")]
+    EmptyWord { digit: u32 },
+    #[error("`{word}` can't spell {digit}, it already spells {spelled}")]
+    Conflict {
+        word: String,
+        digit: u32,
+        spelled: u32,
+    },
+}
+
+// Checks a spelling against the ones already known, and against the digits themselves.
+fn check_word(
+    known: &[(String, u32)],
+    word: String,
+    digit: u32,
+) -> Result<(String, u32), VocabularyError> {
+    if !(1..=9).contains(&digit) {
+        return Err(VocabularyError::InvalidDigit { word, digit });
+    }
+    if word.is_empty() {
+        return Err(VocabularyError::EmptyWord { digit });
+    }
+    let spelled = known
+        .iter()
+        .find(|(known, _)| *known == word)
+        .map(|&(_, spelled)| spelled)
+        .or_else(|| word.parse::<u32>().ok().filter(|_| word.len() == 1));
+    match spelled {
+        Some(spelled) if spelled != digit => Err(VocabularyError::Conflict {
+            word,
+            digit,
+            spelled,
+        }),
+        _ => Ok((word, digit)),
+    }
+}
+
+fn check_words<W: Into<String>>(
+    words: impl IntoIterator<Item = (W, u32)>,
+) -> Result<Vec<(String, u32)>, VocabularyError> {
+    let mut checked = Vec::new();
+    for (word, digit) in words {
+        let word = check_word(&checked, word.into(), digit)?;
+        checked.push(word);
+    }
+    Ok(checked)
+}
+
+pub struct Day1;
+
+impl Solution for Day1 {
+    type Input<'a> = Vec<&'a str>;
+    type Answer = u32;
+    type Error = ParseError;
+
+    fn parse(input: &str) -> Result<Self::Input<'_>, Self::Error> {
+        Ok(input.lines().collect())
+    }
+
+    fn part_one(input: &Self::Input<'_>) -> Result<Self::Answer, Self::Error> {
+        sum_of_calibrations(input)
+    }
+
+    fn part_two(input: &Self::Input<'_>) -> Option<Result<Self::Answer, Self::Error>> {
+        Some(sum_of_calibrations_with_words(
+            input,
+            &DigitVocabulary::english(),
+        ))
+    }
+}
+
+fn sum_of_calibrations(lines: &[&str]) -> Result<u32, ParseError> {
     let mut calculations = Vec::new();
-    for line in calibrations.lines() {
-        let digits= line
+    for (idx, line) in lines.iter().enumerate() {
+        let digits: Vec<u32> = line
             .chars()
             .filter(|c| c.is_numeric())
-            .filter_map(|c| c.to_digit(10));
+            .filter_map(|c| c.to_digit(10))
+            .collect();
 
-        let first_digit = digits.first().expect("Expected a u32");
-        let second_digit = digits.last().expect("Expected a u32");
+        let (Some(first_digit), Some(second_digit)) = (digits.first(), digits.last()) else {
+            return Err(ParseError::missing_digit(idx, line));
+        };
 
         let calculation = (*first_digit * 10) + second_digit;
         calculations.push(calculation);
     }
-    calculations.iter().sum()
+    Ok(calculations.iter().sum())
 }
 
-pub fn trebuchet_launch_with_words(calibrations: &str) -> u32 {
-    // Too lazy to write a Trie or graph solution.
-    let mut calculations = Vec::new();
-    let mut digit_strings = std::collections::HashMap::new();
-    digit_strings.insert("one".to_owned(), 1);
-    digit_strings.insert("two".to_owned(), 2);
-    digit_strings.insert("three".to_owned(), 3);
-    digit_strings.insert("four".to_owned(), 4);
-    digit_strings.insert("five".to_owned(), 5);
-    digit_strings.insert("six".to_owned(), 6);
-    digit_strings.insert("seven".to_owned(), 7);
-    digit_strings.insert("eight".to_owned(), 8);
-    digit_strings.insert("nine".to_owned(), 9);
-
-    for line in calibrations.lines() {
-        let mut first_digit = None;
-        let mut last_digit = None;
-
-        let mut first_word = String::new();
-        let mut last_word = String::new();
-
-        for (first_char, last_char) in line.chars().zip(line.chars().rev()) {
-            match first_char {
-                'w' | 'e' | 's' | 'h' | 'o' | 'g' | 'n' | 't' | 'f' | 'u' | 'r' | 'v' | 'x'
-                | 'i' => first_word.push(first_char),
-                '1'..='9' => {
-                    if first_digit.is_none() {
-                        first_digit = first_char.to_digit(10)
-                    }
-                }
-                _ => (),
-            }
+const ENGLISH: [(&str, u32); 9] = [
+    ("one", 1),
+    ("two", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("seven", 7),
+    ("eight", 8),
+    ("nine", 9),
+];
+
+const GERMAN: [(&str, u32); 9] = [
+    ("eins", 1),
+    ("zwei", 2),
+    ("drei", 3),
+    ("vier", 4),
+    ("fünf", 5),
+    ("sechs", 6),
+    ("sieben", 7),
+    ("acht", 8),
+    ("neun", 9),
+];
+
+const FRENCH: [(&str, u32); 9] = [
+    ("un", 1),
+    ("deux", 2),
+    ("trois", 3),
+    ("quatre", 4),
+    ("cinq", 5),
+    ("six", 6),
+    ("sept", 7),
+    ("huit", 8),
+    ("neuf", 9),
+];
+
+const SPANISH: [(&str, u32); 9] = [
+    ("uno", 1),
+    ("dos", 2),
+    ("tres", 3),
+    ("cuatro", 4),
+    ("cinco", 5),
+    ("seis", 6),
+    ("siete", 7),
+    ("ocho", 8),
+    ("nueve", 9),
+];
+
+/// The words a calibration document spells its digits with, on top of the digits themselves.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DigitVocabulary {
+    words: Vec<(String, u32)>,
+}
+
+impl DigitVocabulary {
+    /// Fails if a word is empty, spells something outside 1 to 9, or is given two different
+    /// digits.
+    pub fn new<W: Into<String>>(
+        words: impl IntoIterator<Item = (W, u32)>,
+    ) -> Result<Self, VocabularyError> {
+        Ok(Self {
+            words: check_words(words)?,
+        })
+    }
+
+    // The built in languages are known to be good.
+    fn builtin(words: [(&str, u32); 9]) -> Self {
+        Self::new(words).expect("built in vocabularies only spell 1 to 9")
+    }
+
+    pub fn english() -> Self {
+        Self::builtin(ENGLISH)
+    }
+
+    pub fn german() -> Self {
+        Self::builtin(GERMAN)
+    }
+
+    pub fn french() -> Self {
+        Self::builtin(FRENCH)
+    }
+
+    pub fn spanish() -> Self {
+        Self::builtin(SPANISH)
+    }
+
+    /// Adds another spelling, e.g. to mix languages or pick up a regional variant.
+    pub fn word(mut self, word: impl Into<String>, digit: u32) -> Result<Self, VocabularyError> {
+        let word = check_word(&self.words, word.into(), digit)?;
+        self.words.push(word);
+        Ok(self)
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.words
+            .iter()
+            .map(|(word, digit)| (word.as_str(), *digit))
+    }
+
+    pub fn matcher(&self) -> DigitMatcher {
+        DigitMatcher::new(self.words()).expect("every word was checked when it was added")
+    }
+}
 
-            match last_char {
-                'w' | 'e' | 's' | 'h' | 'o' | 'g' | 'n' | 't' | 'f' | 'u' | 'r' | 'v' | 'x'
-                | 'i' => last_word.insert(0, last_char),
-                '1'..='9' => {
-                    if last_digit.is_none() {
-                        last_digit = last_char.to_digit(10)
-                    }
+#[derive(Debug, Default)]
+struct MatcherNode {
+    next: HashMap<char, usize>,
+    fail: usize,
+    // The digit spelled by the path to this node and its length in bytes.
+    output: Option<(u32, usize)>,
+    // The closest node along the fail links that spells a digit, so overlapping matches that end
+    // on the same character are all reported.
+    suffix_output: Option<usize>,
+}
+
+/// An Aho–Corasick automaton over the digits `1`–`9` and a set of spelled out words, which finds
+/// every match in a line in a single pass, overlapping ones included.
+#[derive(Debug)]
+pub struct DigitMatcher {
+    nodes: Vec<MatcherNode>,
+}
+
+impl DigitMatcher {
+    /// Fails on the same words as [`DigitVocabulary::new`].
+    pub fn new<'w>(
+        words: impl IntoIterator<Item = (&'w str, u32)>,
+    ) -> Result<Self, VocabularyError> {
+        let mut matcher = DigitMatcher {
+            nodes: vec![MatcherNode::default()],
+        };
+        let digits = ('1'..='9')
+            .zip(1..)
+            .map(|(c, digit)| (c.to_string(), digit));
+        for (pattern, digit) in digits {
+            matcher.insert(&pattern, digit);
+        }
+        for (word, digit) in check_words(words)? {
+            matcher.insert(&word, digit);
+        }
+        matcher.link();
+        Ok(matcher)
+    }
+
+    fn insert(&mut self, pattern: &str, digit: u32) {
+        if pattern.is_empty() {
+            return;
+        }
+        let mut node = 0;
+        for c in pattern.chars() {
+            node = match self.nodes[node].next.get(&c) {
+                Some(&next) => next,
+                None => {
+                    self.nodes.push(MatcherNode::default());
+                    let next = self.nodes.len() - 1;
+                    self.nodes[node].next.insert(c, next);
+                    next
                 }
-                _ => (),
+            };
+        }
+        self.nodes[node].output = Some((digit, pattern.len()));
+    }
+
+    // Breadth first, so every node's fail link points at a shallower node that is already linked.
+    fn link(&mut self) {
+        let mut queue: VecDeque<usize> = self.nodes[0].next.values().copied().collect();
+        while let Some(node) = queue.pop_front() {
+            let children: Vec<(char, usize)> = self.nodes[node]
+                .next
+                .iter()
+                .map(|(&c, &n)| (c, n))
+                .collect();
+            for (c, child) in children {
+                let fail = self.advance(self.nodes[node].fail, c);
+                self.nodes[child].fail = fail;
+                self.nodes[child].suffix_output = match self.nodes[fail].output {
+                    Some(_) => Some(fail),
+                    None => self.nodes[fail].suffix_output,
+                };
+                queue.push_back(child);
             }
+        }
+    }
 
-            // Brute force check. If I used a Trie I could just traverse the graph based on the
-            // seen characters.
-            for (k, v) in &digit_strings {
-                if first_word.contains(k) && first_digit.is_none() {
-                    first_digit = Some(*v);
-                }
-                if last_word.contains(k) && last_digit.is_none() {
-                    last_digit = Some(*v);
-                }
+    fn advance(&self, mut node: usize, c: char) -> usize {
+        loop {
+            if let Some(&next) = self.nodes[node].next.get(&c) {
+                return next;
+            }
+            if node == 0 {
+                return 0;
             }
+            node = self.nodes[node].fail;
+        }
+    }
+
+    /// Every `(position, digit)` in `line`, where the position is the byte offset the match
+    /// starts at. Matches come out in the order they end, so `oneight` gives both `1` and `8`.
+    pub fn matches<'m, 'l>(&'m self, line: &'l str) -> Matches<'m, 'l> {
+        Matches {
+            matcher: self,
+            chars: line.char_indices(),
+            state: 0,
+            end: 0,
+            pending: None,
+        }
+    }
+
+    /// The digits that start first and last in `line`.
+    pub fn first_and_last(&self, line: &str) -> Option<(u32, u32)> {
+        let mut matches = self.matches(line);
+        let first = matches.next()?;
+        let (first, last) = matches.fold((first, first), |(first, last), found| {
+            (first.min(found), last.max(found))
+        });
+        Some((first.1, last.1))
+    }
+}
+
+pub struct Matches<'m, 'l> {
+    matcher: &'m DigitMatcher,
+    chars: std::str::CharIndices<'l>,
+    state: usize,
+    end: usize,
+    pending: Option<usize>,
+}
+
+impl Iterator for Matches<'_, '_> {
+    type Item = (usize, u32);
 
-            if first_digit.is_some() && last_digit.is_some() {
-                break;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(node) = self.pending {
+                let node = &self.matcher.nodes[node];
+                self.pending = node.suffix_output;
+                if let Some((digit, len)) = node.output {
+                    return Some((self.end - len, digit));
+                }
+                continue;
             }
+            let (offset, c) = self.chars.next()?;
+            self.end = offset + c.len_utf8();
+            self.state = self.matcher.advance(self.state, c);
+            self.pending = Some(self.state);
         }
+    }
+}
 
-        let first_digit = first_digit.expect("Expected a valid digit.");
-        let last_digit = last_digit.expect("Expected a valid digit.");
+fn sum_of_calibrations_with_words(
+    lines: &[&str],
+    vocabulary: &DigitVocabulary,
+) -> Result<u32, ParseError> {
+    let matcher = vocabulary.matcher();
+    let mut calculations = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let Some((first_digit, last_digit)) = matcher.first_and_last(line) else {
+            return Err(ParseError::missing_digit(idx, line));
+        };
 
         let calculation = (first_digit * 10) + last_digit;
         calculations.push(calculation);
     }
-    calculations.iter().sum()
+    Ok(calculations.iter().sum())
+}
+
+pub fn trebuchet_launch(calibrations: &str) -> Result<u32, ParseError> {
+    sum_of_calibrations(&Day1::parse(calibrations)?)
+}
+
+pub fn trebuchet_launch_with_words(
+    calibrations: &str,
+    vocabulary: &DigitVocabulary,
+) -> Result<u32, ParseError> {
+    sum_of_calibrations_with_words(&Day1::parse(calibrations)?, vocabulary)
 }
 
 #[cfg(test)]
@@ -94,25 +396,108 @@ mod tests {
 
     #[test]
     fn check_sample() {
-        let result = trebuchet_launch(SAMPLE_INPUT);
+        let result = trebuchet_launch(SAMPLE_INPUT).unwrap();
         assert_eq!(result, 142);
     }
 
     #[test]
     fn check_input() {
-        let result = trebuchet_launch(INPUT);
+        let result = trebuchet_launch(INPUT).unwrap();
         assert_eq!(result, 55621);
     }
 
     #[test]
     fn check_sample_with_words() {
-        let result = trebuchet_launch_with_words(SAMPLE_INPUT_WITH_WORDS);
+        let result =
+            trebuchet_launch_with_words(SAMPLE_INPUT_WITH_WORDS, &DigitVocabulary::english())
+                .unwrap();
         assert_eq!(result, 281);
     }
 
     #[test]
     fn check_input_with_words() {
-        let result = trebuchet_launch_with_words(INPUT_WITH_WORDS);
+        let result =
+            trebuchet_launch_with_words(INPUT_WITH_WORDS, &DigitVocabulary::english()).unwrap();
         assert_eq!(result, 53592);
     }
+
+    #[test]
+    fn overlapping_words() {
+        let matcher = DigitVocabulary::english().matcher();
+        let matches: Vec<(usize, u32)> = matcher.matches("oneight").collect();
+        assert_eq!(matches, [(0, 1), (2, 8)]);
+        let matches: Vec<(usize, u32)> = matcher.matches("xtwone3four").collect();
+        assert_eq!(matches, [(1, 2), (3, 1), (6, 3), (7, 4)]);
+
+        assert_eq!(matcher.first_and_last("twone"), Some((2, 1)));
+        assert_eq!(matcher.first_and_last("7pqrstsixteen"), Some((7, 6)));
+        assert_eq!(matcher.first_and_last("sevenine"), Some((7, 9)));
+        assert_eq!(matcher.first_and_last("pqrstu"), None);
+    }
+
+    #[test]
+    fn other_languages() {
+        let lines = "zweiundvierzig\nxfünfzehn3";
+        let result = trebuchet_launch_with_words(lines, &DigitVocabulary::german()).unwrap();
+        assert_eq!(result, 24 + 53);
+
+        let result =
+            trebuchet_launch_with_words("quatre-vingt-dix-neuf", &DigitVocabulary::french());
+        assert_eq!(result, Ok(49));
+        let result = trebuchet_launch_with_words("dosmilveintitres", &DigitVocabulary::spanish());
+        assert_eq!(result, Ok(23));
+
+        let greek = DigitVocabulary::new([("ένα", 1), ("δύο", 2)])
+            .and_then(|greek| greek.word("τρία", 3))
+            .unwrap();
+        let matches: Vec<(usize, u32)> = greek.matcher().matches("xδύοένα").collect();
+        assert_eq!(matches, [(1, 2), (7, 1)]);
+        let result = trebuchet_launch_with_words("τρίαxδύο\n5ένα", &greek);
+        assert_eq!(result, Ok(32 + 51));
+    }
+
+    #[test]
+    fn only_single_digits() {
+        let result = DigitVocabulary::english().word("ten", 10);
+        assert_eq!(
+            result,
+            Err(VocabularyError::InvalidDigit {
+                word: "ten".to_owned(),
+                digit: 10,
+            })
+        );
+        assert!(DigitVocabulary::new([("zero", 0)]).is_err());
+        assert!(DigitMatcher::new([("null", 0)]).is_err());
+        assert!(DigitVocabulary::english().word("nine", 9).is_ok());
+
+        let result = DigitVocabulary::french().word("six", 7);
+        assert_eq!(
+            result,
+            Err(VocabularyError::Conflict {
+                word: "six".to_owned(),
+                digit: 7,
+                spelled: 6,
+            })
+        );
+        assert!(DigitVocabulary::english().word("six", 6).is_ok());
+        assert!(DigitMatcher::new([("uno", 1), ("uno", 2)]).is_err());
+        assert!(DigitVocabulary::new([("3", 5)]).is_err());
+        assert_eq!(
+            DigitVocabulary::new([("", 4)]),
+            Err(VocabularyError::EmptyWord { digit: 4 })
+        );
+    }
+
+    #[test]
+    fn check_missing_digit() {
+        let result = trebuchet_launch("1abc2\npqrstu\n");
+        assert_eq!(
+            result,
+            Err(ParseError::MissingDigit {
+                location: Location::new(2, 1),
+                token: "pqrstu".to_owned(),
+            })
+        );
+        assert!(trebuchet_launch_with_words("pqrstuone", &DigitVocabulary::english()).is_ok());
+    }
 }
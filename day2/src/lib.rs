@@ -1,95 +1,277 @@
-pub fn possible_games(input: &str, red_cubes: u32, green_cubes: u32, blue_cubes: u32) -> u32 {
-    let mut valid_games = 0;
-    for line in input.lines() {
-        let mut line = line.split(": ");
-        let game_id = line.next().expect("Missing a :");
-        let game_id = game_id
-            .split(" ")
-            .nth(1)
-            .expect("Expected a space between Game and game id.");
-        let game_id = u32::from_str_radix(game_id, 10).expect("Unable to parse u32");
-
-        let game_sequence = line.next().expect("Game sequence starts after :");
-        let mut valid_game = true;
-        for set in game_sequence.split(";") {
-            for color in set.split(",") {
-                let mut count = 0;
-                for item in color.split(" ") {
-                    match item {
-                        "red" => {
-                            if count > red_cubes {
-                                valid_game = false;
-                            }
-                        }
-                        "green" => {
-                            if count > green_cubes {
-                                valid_game = false;
-                            }
-                        }
-                        "blue" => {
-                            if count > blue_cubes {
-                                valid_game = false;
-                            }
-                        }
-                        _ => {
-                            if let Ok(color_count) = u32::from_str_radix(item, 10) {
-                                count = color_count;
-                            }
-                        }
-                    }
-                }
-            }
+use aoc_core::{Location, Solution};
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+pub mod likelihood;
+
+const BAG: [(&str, u32); 3] = [("red", 12), ("green", 13), ("blue", 14)];
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("{location}: expected `Game <id>: ` but found `{token}`")]
+    MissingColon { location: Location, token: String },
+    #[error("{location}: invalid game id `{token}`")]
+    InvalidGameId { location: Location, token: String },
+    #[error("{location}: expected `<count> <color>` but found `{token}`")]
+    MissingColor { location: Location, token: String },
+    #[error("{location}: invalid cube count `{token}`")]
+    InvalidCount { location: Location, token: String },
+    #[error("{location}: unknown color `{token}`")]
+    UnknownColor { location: Location, token: String },
+    #[error("{location}: too many `{token}` cubes to count in one round")]
+    TooManyCubes { location: Location, token: String },
+    #[error("{0} doesn't fit in a u64")]
+    Overflow(&'static str),
+}
+
+/// What to do with a color the palette doesn't know about.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnknownColors {
+    #[default]
+    Reject,
+    Count,
+}
+
+/// The colors a game record is expected to use.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    colors: BTreeSet<String>,
+    unknown: UnknownColors,
+}
+
+impl Palette {
+    pub fn new<C: Into<String>>(colors: impl IntoIterator<Item = C>) -> Self {
+        Self {
+            colors: colors.into_iter().map(Into::into).collect(),
+            unknown: UnknownColors::default(),
+        }
+    }
+
+    /// Accepts whatever colors show up.
+    pub fn any() -> Self {
+        Self::new(Vec::<String>::new()).unknown_colors(UnknownColors::Count)
+    }
+
+    pub fn unknown_colors(mut self, unknown: UnknownColors) -> Self {
+        self.unknown = unknown;
+        self
+    }
+
+    fn accepts(&self, color: &str) -> bool {
+        self.unknown == UnknownColors::Count || self.colors.contains(color)
+    }
+}
+
+/// The puzzle's red, green and blue cubes.
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new(BAG.map(|(color, _)| color))
+    }
+}
+
+/// A number of cubes for each color. Colors that aren't in the set count as zero.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CubeSet {
+    counts: BTreeMap<String, u32>,
+}
+
+impl<C: Into<String>> FromIterator<(C, u32)> for CubeSet {
+    fn from_iter<I: IntoIterator<Item = (C, u32)>>(iter: I) -> Self {
+        let mut set = CubeSet::default();
+        for (color, count) in iter {
+            set.add(color, count);
+        }
+        set
+    }
+}
+
+impl CubeSet {
+    pub fn get(&self, color: &str) -> u32 {
+        self.counts.get(color).copied().unwrap_or(0)
+    }
+
+    /// Adds cubes of a color, stopping at `u32::MAX` of them.
+    pub fn add(&mut self, color: impl Into<String>, count: u32) {
+        let total = self.counts.entry(color.into()).or_insert(0);
+        *total = total.saturating_add(count);
+    }
+
+    /// Adds cubes of a color, or leaves the set alone and returns `None` when the count would
+    /// overflow.
+    pub fn checked_add(&mut self, color: impl Into<String>, count: u32) -> Option<u32> {
+        let total = self.counts.entry(color.into()).or_insert(0);
+        *total = total.checked_add(count)?;
+        Some(*total)
+    }
+
+    pub fn colors(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.counts
+            .iter()
+            .map(|(color, count)| (color.as_str(), *count))
+    }
+
+    /// Whether every cube in `other` could have come out of this set.
+    pub fn contains(&self, other: &CubeSet) -> bool {
+        other
+            .colors()
+            .all(|(color, count)| count <= self.get(color))
+    }
+
+    /// The product of the counts of every color in the set, or zero for an empty set. `None` when
+    /// the product doesn't fit in a `u64`.
+    pub fn power(&self) -> Option<u64> {
+        if self.counts.is_empty() {
+            return Some(0);
         }
-        if valid_game {
-            valid_games += game_id;
+        self.counts
+            .values()
+            .try_fold(1_u64, |power, &count| power.checked_mul(count.into()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Game {
+    pub id: u32,
+    pub rounds: Vec<CubeSet>,
+}
+
+impl Game {
+    /// Parses a single `Game <id>: ...` record against `palette`. Errors are located on line 1.
+    pub fn parse_with(line: &str, palette: &Palette) -> Result<Self, ParseError> {
+        parse_game(0, line, palette)
+    }
+
+    /// Whether every round could have been drawn from `bag`.
+    pub fn is_possible(&self, bag: &CubeSet) -> bool {
+        self.rounds.iter().all(|round| bag.contains(round))
+    }
+
+    /// The fewest cubes of each color the bag must have held.
+    pub fn minimum_bag(&self) -> CubeSet {
+        let mut bag = CubeSet::default();
+        for (color, count) in self.rounds.iter().flat_map(CubeSet::colors) {
+            let most = bag.counts.entry(color.to_owned()).or_insert(0);
+            *most = (*most).max(count);
         }
+        bag
     }
 
-    valid_games
-}
-
-pub fn power_of_possible_games(input: &str) -> u32 {
-    let mut power_of_games = 0;
-    for line in input.lines() {
-        let mut line = line.split(": ");
-        let game_sequence = line.nth(1).expect("Game sequence starts after :");
-
-        let mut red = 0;
-        let mut green = 0;
-        let mut blue = 0;
-        for set in game_sequence.split(";") {
-            for color in set.split(",") {
-                let mut count = 0;
-                for item in color.split(" ") {
-                    match item {
-                        "red" => {
-                            if count > red {
-                                red = count;
-                            }
-                        }
-                        "green" => {
-                            if count > green {
-                                green = count;
-                            }
-                        }
-                        "blue" => {
-                            if count > blue {
-                                blue = count;
-                            }
-                        }
-                        _ => {
-                            if let Ok(color_count) = u32::from_str_radix(item, 10) {
-                                count = color_count;
-                            }
-                        }
-                    }
-                }
+    pub fn power(&self) -> Option<u64> {
+        self.minimum_bag().power()
+    }
+}
+
+impl FromStr for Game {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Game::parse_with(s, &Palette::default())
+    }
+}
+
+fn parse_game(idx: usize, line: &str, palette: &Palette) -> Result<Game, ParseError> {
+    let Some((header, game_sequence)) = line.split_once(": ") else {
+        return Err(ParseError::MissingColon {
+            location: Location::of(idx, line, line),
+            token: line.to_owned(),
+        });
+    };
+    let id = header
+        .strip_prefix("Game ")
+        .and_then(|id| id.parse::<u32>().ok())
+        .ok_or_else(|| ParseError::InvalidGameId {
+            location: Location::of(idx, line, header),
+            token: header.to_owned(),
+        })?;
+
+    let mut rounds = Vec::new();
+    for set in game_sequence.split(';') {
+        let mut round = CubeSet::default();
+        for color in set.split(',') {
+            let color = color.trim();
+            let Some((count, name)) = color.split_once(' ') else {
+                return Err(ParseError::MissingColor {
+                    location: Location::of(idx, line, color),
+                    token: color.to_owned(),
+                });
+            };
+            let count = count.parse::<u32>().map_err(|_| ParseError::InvalidCount {
+                location: Location::of(idx, line, count),
+                token: count.to_owned(),
+            })?;
+            if !palette.accepts(name) {
+                return Err(ParseError::UnknownColor {
+                    location: Location::of(idx, line, name),
+                    token: name.to_owned(),
+                });
+            }
+            if round.checked_add(name, count).is_none() {
+                return Err(ParseError::TooManyCubes {
+                    location: Location::of(idx, line, name),
+                    token: name.to_owned(),
+                });
             }
         }
-        power_of_games += red * blue * green;
+        rounds.push(round);
+    }
+
+    Ok(Game { id, rounds })
+}
+
+/// Parses every game in `input` against `palette`.
+pub fn parse_games(input: &str, palette: &Palette) -> Result<Vec<Game>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| parse_game(idx, line, palette))
+        .collect()
+}
+
+pub struct Day2;
+
+impl Solution for Day2 {
+    type Input<'a> = Vec<Game>;
+    type Answer = u64;
+    type Error = ParseError;
+
+    fn parse(input: &str) -> Result<Self::Input<'_>, Self::Error> {
+        parse_games(input, &Palette::default())
+    }
+
+    fn part_one(input: &Self::Input<'_>) -> Result<Self::Answer, Self::Error> {
+        Ok(sum_of_possible_game_ids(input, &CubeSet::from_iter(BAG)))
+    }
+
+    fn part_two(input: &Self::Input<'_>) -> Option<Result<Self::Answer, Self::Error>> {
+        Some(sum_of_powers(input))
     }
+}
+
+pub fn possible_games(input: &str, bag: &CubeSet) -> Result<u64, ParseError> {
+    let games = Day2::parse(input)?;
+    Ok(sum_of_possible_game_ids(&games, bag))
+}
 
-    power_of_games
+pub fn power_of_possible_games(input: &str) -> Result<u64, ParseError> {
+    sum_of_powers(&Day2::parse(input)?)
+}
+
+// Ids are `u32`s, so even `u32::MAX` games of them can't overflow a `u64`.
+fn sum_of_possible_game_ids(games: &[Game], bag: &CubeSet) -> u64 {
+    games
+        .iter()
+        .filter(|game| game.is_possible(bag))
+        .map(|game| u64::from(game.id))
+        .sum()
+}
+
+fn sum_of_powers(games: &[Game]) -> Result<u64, ParseError> {
+    games.iter().try_fold(0_u64, |sum, game| {
+        game.power()
+            .ok_or(ParseError::Overflow("the power of a game"))?
+            .checked_add(sum)
+            .ok_or(ParseError::Overflow("the sum of the powers"))
+    })
 }
 
 #[cfg(test)]
@@ -98,27 +280,134 @@ mod tests {
     const SAMPLE: &str = include_str!("sample.txt");
     const INPUT: &str = include_str!("input.txt");
 
+    fn bag() -> CubeSet {
+        CubeSet::from_iter([("red", 12), ("green", 13), ("blue", 14)])
+    }
+
     #[test]
     fn sample_possible_games() {
-        let result = possible_games(SAMPLE, 12, 13, 14);
+        let result = possible_games(SAMPLE, &bag()).unwrap();
         assert_eq!(result, 8);
     }
 
     #[test]
     fn sample_power_of_games() {
-        let result = power_of_possible_games(SAMPLE);
+        let result = power_of_possible_games(SAMPLE).unwrap();
         assert_eq!(result, 2286);
     }
 
     #[test]
     fn input_possible_games() {
-        let result = possible_games(INPUT, 12, 13, 14);
+        let result = possible_games(INPUT, &bag()).unwrap();
         assert_eq!(result, 2600);
     }
 
     #[test]
     fn input_power_of_games() {
-        let result = power_of_possible_games(INPUT);
+        let result = power_of_possible_games(INPUT).unwrap();
         assert_eq!(result, 86036);
     }
+
+    #[test]
+    fn game_queries() {
+        let game: Game = "Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red"
+            .parse()
+            .unwrap();
+        assert_eq!(game.id, 3);
+        assert_eq!(game.rounds.len(), 3);
+        assert_eq!(
+            game.rounds[1],
+            CubeSet::from_iter([("red", 4), ("green", 13), ("blue", 5)])
+        );
+
+        assert_eq!(
+            game.minimum_bag(),
+            CubeSet::from_iter([("red", 20), ("green", 13), ("blue", 6)])
+        );
+        assert_eq!(game.power(), Some(1560));
+        assert!(!game.is_possible(&bag()));
+        assert!(game.is_possible(&game.minimum_bag()));
+
+        let result = "Game x: 1 red".parse::<Game>();
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidGameId {
+                location: Location::new(1, 1),
+                token: "Game x".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn other_colors() {
+        let line = "Game 7: 2 cyan, 1 red; 5 magenta, 3 cyan";
+        let palette = Palette::new(["cyan", "magenta", "red"]);
+        let game = Game::parse_with(line, &palette).unwrap();
+        assert_eq!(game.minimum_bag().get("cyan"), 3);
+        assert_eq!(game.minimum_bag().get("blue"), 0);
+        assert_eq!(game.power(), Some(3 * 5));
+        assert!(game.is_possible(&CubeSet::from_iter([
+            ("cyan", 3),
+            ("magenta", 5),
+            ("red", 1)
+        ])));
+        assert!(!game.is_possible(&bag()));
+
+        let result = line.parse::<Game>();
+        assert_eq!(
+            result,
+            Err(ParseError::UnknownColor {
+                location: Location::new(1, 11),
+                token: "cyan".to_owned(),
+            })
+        );
+        let palette = Palette::default().unknown_colors(UnknownColors::Count);
+        assert_eq!(Game::parse_with(line, &palette), Ok(game.clone()));
+        assert_eq!(Game::parse_with(line, &Palette::any()), Ok(game));
+    }
+
+    #[test]
+    fn malformed_games() {
+        let result = possible_games("Game 1: 3 blue, 4 purple", &bag());
+        assert_eq!(
+            result,
+            Err(ParseError::UnknownColor {
+                location: Location::new(1, 19),
+                token: "purple".to_owned(),
+            })
+        );
+
+        let result = power_of_possible_games("Game 1: 3 blue\nGame 2: x red");
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidCount {
+                location: Location::new(2, 9),
+                token: "x".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn huge_counts() {
+        let result = power_of_possible_games("Game 1: 100000 red, 100000 blue, 100000 green");
+        assert_eq!(result, Ok(100000 * 100000 * 100000));
+
+        let palette = Palette::any();
+        let line = "Game 1: 100000 a, 100000 b, 100000 c, 100000 d";
+        let game = Game::parse_with(line, &palette).unwrap();
+        assert_eq!(game.power(), None);
+        assert_eq!(
+            sum_of_powers(&[game]),
+            Err(ParseError::Overflow("the power of a game"))
+        );
+
+        let result = "Game 1: 4294967295 red, 1 red".parse::<Game>();
+        assert_eq!(
+            result,
+            Err(ParseError::TooManyCubes {
+                location: Location::new(1, 27),
+                token: "red".to_owned(),
+            })
+        );
+    }
 }
@@ -0,0 +1,213 @@
+//! How likely each game is for a given bag, assuming every round is a single handful drawn without
+//! replacement and put back before the next round.
+
+use crate::{CubeSet, Game};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::BTreeSet;
+
+fn ln_choose(n: u32, k: u32) -> f64 {
+    (0..k)
+        .map(|i| f64::from(n - i).ln() - f64::from(i + 1).ln())
+        .sum()
+}
+
+fn ln_round_likelihood(bag: &CubeSet, round: &CubeSet) -> f64 {
+    let total = bag.colors().map(|(_, count)| count).sum::<u32>();
+    let drawn = round.colors().map(|(_, count)| count).sum::<u32>();
+    if drawn > total || round.colors().any(|(color, count)| count > bag.get(color)) {
+        return f64::NEG_INFINITY;
+    }
+
+    // Multivariate hypergeometric: every way to pick the shown cubes of each color out of every
+    // way to pick a handful of that size.
+    let ways = round
+        .colors()
+        .map(|(color, count)| ln_choose(bag.get(color), count))
+        .sum::<f64>();
+    ways - ln_choose(total, drawn)
+}
+
+/// The probability of drawing exactly `round` in one handful from `bag`.
+pub fn round_likelihood(bag: &CubeSet, round: &CubeSet) -> f64 {
+    ln_round_likelihood(bag, round).exp()
+}
+
+pub fn game_likelihood(bag: &CubeSet, game: &Game) -> f64 {
+    ln_game_likelihood(bag, game).exp()
+}
+
+fn ln_game_likelihood(bag: &CubeSet, game: &Game) -> f64 {
+    game.rounds
+        .iter()
+        .map(|round| ln_round_likelihood(bag, round))
+        .sum()
+}
+
+/// The likelihood of every game against the same bag, by game id.
+pub fn likelihoods(bag: &CubeSet, games: &[Game]) -> Vec<(u32, f64)> {
+    games
+        .iter()
+        .map(|game| (game.id, game_likelihood(bag, game)))
+        .collect()
+}
+
+/// The log-likelihood of all games together. Games are independent, so this is a plain sum.
+pub fn log_likelihood(bag: &CubeSet, games: &[Game]) -> f64 {
+    games.iter().map(|game| ln_game_likelihood(bag, game)).sum()
+}
+
+/// The bag that makes the games most likely, with at most `limit` cubes of any one color. Every
+/// bag between the smallest one that allows every game and the limit is tried, and ties go to the
+/// bag with fewer cubes. Returns `None` when some game needs more than `limit` cubes of a color.
+pub fn estimate_bag(games: &[Game], limit: u32) -> Option<CubeSet> {
+    let colors: BTreeSet<&str> = games
+        .iter()
+        .flat_map(|game| game.rounds.iter().flat_map(CubeSet::colors))
+        .map(|(color, _)| color)
+        .collect();
+    let colors: Vec<&str> = colors.into_iter().collect();
+    let minimum: Vec<u32> = colors
+        .iter()
+        .map(|color| {
+            let needed = games.iter().map(|game| game.minimum_bag().get(color));
+            needed.max().unwrap_or(0)
+        })
+        .collect();
+    if minimum.iter().any(|&count| count > limit) {
+        return None;
+    }
+
+    let mut counts = minimum.clone();
+    let mut best: Option<(f64, u64, CubeSet)> = None;
+    loop {
+        let bag: CubeSet = colors.iter().copied().zip(counts.iter().copied()).collect();
+        let likelihood = log_likelihood(&bag, games);
+        let total = counts.iter().map(|&count| u64::from(count)).sum();
+        // The odometer doesn't visit bags in order of size, so ties have to be settled here.
+        let better = |(best, best_total, _): &(f64, u64, CubeSet)| {
+            likelihood > *best || (likelihood == *best && total < *best_total)
+        };
+        if best.as_ref().is_none_or(better) {
+            best = Some((likelihood, total, bag));
+        }
+
+        // Step to the next bag like an odometer, rolling each color back to its minimum.
+        let Some(color) = counts.iter().position(|&count| count < limit) else {
+            break;
+        };
+        counts[color] += 1;
+        counts[..color].copy_from_slice(&minimum[..color]);
+    }
+    best.map(|(_, _, bag)| bag)
+}
+
+/// Estimates the same likelihoods by actually drawing handfuls, to cross-check the exact numbers.
+pub struct MonteCarlo {
+    trials: u32,
+    rng: StdRng,
+}
+
+impl MonteCarlo {
+    /// Seeded so that repeated runs give the same estimates.
+    pub fn new(trials: u32, seed: u64) -> Self {
+        Self {
+            trials,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn round_likelihood(&mut self, bag: &CubeSet, round: &CubeSet) -> f64 {
+        let drawn = round.colors().map(|(_, count)| count).sum::<u32>() as usize;
+        let mut cubes: Vec<&str> = bag
+            .colors()
+            .flat_map(|(color, count)| std::iter::repeat_n(color, count as usize))
+            .collect();
+        if drawn > cubes.len() || self.trials == 0 {
+            return 0.0;
+        }
+
+        let mut hits = 0;
+        for _ in 0..self.trials {
+            let (handful, _) = cubes.partial_shuffle(&mut self.rng, drawn);
+            let handful: CubeSet = handful.iter().map(|&color| (color, 1)).collect();
+            if round
+                .colors()
+                .all(|(color, count)| handful.get(color) == count)
+            {
+                hits += 1;
+            }
+        }
+        f64::from(hits) / f64::from(self.trials)
+    }
+
+    pub fn game_likelihood(&mut self, bag: &CubeSet, game: &Game) -> f64 {
+        game.rounds
+            .iter()
+            .map(|round| self.round_likelihood(bag, round))
+            .product()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bag() -> CubeSet {
+        CubeSet::from_iter([("red", 12), ("green", 13), ("blue", 14)])
+    }
+
+    #[test]
+    fn exact_likelihoods() {
+        let bag = CubeSet::from_iter([("red", 2), ("blue", 1)]);
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+        assert!(close(
+            round_likelihood(&bag, &CubeSet::from_iter([("red", 1)])),
+            2.0 / 3.0
+        ));
+        let both = CubeSet::from_iter([("red", 1), ("blue", 1)]);
+        assert!(close(round_likelihood(&bag, &both), 2.0 / 3.0));
+        assert_eq!(
+            round_likelihood(&bag, &CubeSet::from_iter([("blue", 2)])),
+            0.0
+        );
+
+        let game: Game = "Game 1: 1 red; 1 red, 1 blue".parse().unwrap();
+        assert!(close(game_likelihood(&bag, &game), 4.0 / 9.0));
+    }
+
+    #[test]
+    fn monte_carlo_agrees() {
+        let game: Game = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green"
+            .parse()
+            .unwrap();
+        let mut simulation = MonteCarlo::new(20_000, 2023);
+        for round in &game.rounds {
+            let exact = round_likelihood(&bag(), round);
+            let simulated = simulation.round_likelihood(&bag(), round);
+            assert!(
+                (exact - simulated).abs() < 0.1 * exact + 0.002,
+                "{exact} vs {simulated}"
+            );
+        }
+    }
+
+    #[test]
+    fn most_likely_bag() {
+        let game: Game = "Game 1: 1 red, 1 blue".parse().unwrap();
+        let expected = CubeSet::from_iter([("red", 1), ("blue", 1)]);
+        assert_eq!(estimate_bag(&[game], 10), Some(expected));
+
+        // Drawing nothing is certain whatever the bag holds, so the emptiest bag wins the tie.
+        let game: Game = "Game 1: 0 red, 0 blue".parse().unwrap();
+        let expected = CubeSet::from_iter([("red", 0), ("blue", 0)]);
+        assert_eq!(estimate_bag(&[game], 3), Some(expected));
+
+        let games = crate::parse_games(include_str!("sample.txt"), &Default::default()).unwrap();
+        assert_eq!(estimate_bag(&games, 5), None);
+        let estimate = estimate_bag(&games, 25).unwrap();
+        assert!(games.iter().all(|game| game.is_possible(&estimate)));
+        assert!(log_likelihood(&estimate, &games) >= log_likelihood(&bag(), &games));
+    }
+}
@@ -0,0 +1,183 @@
+//! A rectangular grid of cells addressed by row and column.
+
+use std::ops::Index;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Point {
+    pub fn new(row: usize, col: usize) -> Self {
+        Self { row, col }
+    }
+
+    fn offset(self, (rows, cols): (isize, isize)) -> Option<Point> {
+        Some(Point::new(
+            self.row.checked_add_signed(rows)?,
+            self.col.checked_add_signed(cols)?,
+        ))
+    }
+}
+
+const ORTHOGONAL: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+const SURROUNDING: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+/// Cells are stored row by row, so a row is a plain slice.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Builds a grid out of rows, or `None` if the rows aren't all the same width.
+    pub fn from_rows<R: IntoIterator<Item = T>>(rows: impl IntoIterator<Item = R>) -> Option<Self> {
+        let mut grid = Grid {
+            width: 0,
+            height: 0,
+            cells: Vec::new(),
+        };
+        for row in rows {
+            grid.cells.extend(row);
+            grid.height += 1;
+            if grid.height == 1 {
+                grid.width = grid.cells.len();
+            } else if grid.cells.len() != grid.width * grid.height {
+                return None;
+            }
+        }
+        Some(grid)
+    }
+
+    /// A grid of the same shape with every cell set to `value`.
+    pub fn filled<U: Clone>(&self, value: U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: vec![value; self.cells.len()],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        point.row < self.height && point.col < self.width
+    }
+
+    pub fn get(&self, point: Point) -> Option<&T> {
+        self.contains(point)
+            .then(|| &self.cells[point.row * self.width + point.col])
+    }
+
+    pub fn get_mut(&mut self, point: Point) -> Option<&mut T> {
+        self.contains(point)
+            .then(|| &mut self.cells[point.row * self.width + point.col])
+    }
+
+    pub fn row(&self, row: usize) -> Option<&[T]> {
+        (row < self.height).then(|| &self.cells[row * self.width..(row + 1) * self.width])
+    }
+
+    /// The cells of a column from top to bottom. Empty when the column is out of bounds.
+    pub fn column(&self, col: usize) -> impl Iterator<Item = &T> {
+        let cells = if col < self.width {
+            &self.cells[col..]
+        } else {
+            &[]
+        };
+        cells.iter().step_by(self.width.max(1))
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let width = self.width;
+        (0..self.height).flat_map(move |row| (0..width).map(move |col| Point::new(row, col)))
+    }
+
+    /// The points above, below, left and right of `point` that are inside the grid.
+    pub fn neighbors4(&self, point: Point) -> impl Iterator<Item = Point> + '_ {
+        self.around(point, &ORTHOGONAL)
+    }
+
+    /// Like `neighbors4` but including the diagonals.
+    pub fn neighbors8(&self, point: Point) -> impl Iterator<Item = Point> + '_ {
+        self.around(point, &SURROUNDING)
+    }
+
+    fn around<'g>(
+        &'g self,
+        point: Point,
+        offsets: &'static [(isize, isize)],
+    ) -> impl Iterator<Item = Point> + 'g {
+        offsets
+            .iter()
+            .filter_map(move |&offset| point.offset(offset))
+            .filter(|&neighbor| self.contains(neighbor))
+    }
+}
+
+impl<T> Index<Point> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, point: Point) -> &T {
+        match self.get(point) {
+            Some(cell) => cell,
+            None => panic!("{point:?} is outside a {}x{} grid", self.width, self.height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn views() {
+        let grid = Grid::from_rows(["abc".chars(), "def".chars()]).unwrap();
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid.row(1), Some(&['d', 'e', 'f'][..]));
+        assert_eq!(grid.row(2), None);
+        assert_eq!(grid.column(1).collect::<String>(), "be");
+        assert_eq!(grid.column(3).count(), 0);
+        assert_eq!(grid[Point::new(1, 2)], 'f');
+        assert_eq!(grid.get(Point::new(2, 0)), None);
+        assert_eq!(grid.points().count(), 6);
+
+        assert!(Grid::from_rows(["ab".chars(), "c".chars()]).is_none());
+    }
+
+    #[test]
+    fn neighbors_stay_in_bounds() {
+        let grid = Grid::from_rows(["abc".chars(), "def".chars(), "ghi".chars()]).unwrap();
+        let cells = |points: Vec<Point>| points.into_iter().map(|p| grid[p]).collect::<String>();
+
+        assert_eq!(cells(grid.neighbors8(Point::new(0, 0)).collect()), "bde");
+        assert_eq!(cells(grid.neighbors4(Point::new(0, 0)).collect()), "bd");
+        assert_eq!(
+            cells(grid.neighbors8(Point::new(1, 1)).collect()),
+            "abcdfghi"
+        );
+        assert_eq!(cells(grid.neighbors4(Point::new(2, 2)).collect()), "fh");
+    }
+}
@@ -1,185 +1,335 @@
-use itertools::*;
-use std::collections::{HashMap, HashSet};
-
-#[derive(Debug, Eq, Hash, PartialEq)]
-struct PartsCoordinate {
-    x: usize,
-    y: usize,
-    y_range: (usize, usize),
-    part_number: u32,
-}
-
-pub fn sum_of_engine_parts(input: &str) -> u32 {
-    let mut adjacent_parts = 0;
-
-    let mut symbol_coordinates: Vec<(usize, usize)> = Vec::new();
-    let mut part_numbers: HashMap<(usize, usize), PartsCoordinate> = HashMap::new();
-
-    for (i, line) in input.lines().enumerate() {
-        let mut part_number = None;
-        let mut starting_part_number_coordinate = 0;
-        let mut last_part_number_coordinate = 0;
-        for (j, c) in line.chars().enumerate() {
-            match c {
-                '0'..='9' => {
-                    if let Some(number) = c.to_digit(10) {
-                        if let Some(part) = part_number {
-                            part_number = Some((part * 10) + number);
-                            last_part_number_coordinate = j;
-                        } else {
-                            starting_part_number_coordinate = j;
-                            last_part_number_coordinate = j;
-                            part_number = Some(number);
-                        }
-                    }
+use aoc_core::{Location, Solution};
+
+pub mod grid;
+
+pub use grid::{Grid, Point};
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("{location}: expected a row {expected} characters wide but found `{token}`")]
+    UnevenRow {
+        location: Location,
+        expected: usize,
+        token: String,
+    },
+    #[error("{location}: unexpected character `{token}`")]
+    UnexpectedCharacter { location: Location, token: String },
+    #[error("{location}: part number `{token}` doesn't fit in a u32")]
+    InvalidNumber { location: Location, token: String },
+    #[error("{0} doesn't fit in a u64")]
+    Overflow(&'static str),
+}
+
+fn validate_row(idx: usize, line: &str, width: usize) -> Result<(), ParseError> {
+    if line.chars().count() != width {
+        return Err(ParseError::UnevenRow {
+            location: Location::of(idx, line, line),
+            expected: width,
+            token: line.to_owned(),
+        });
+    }
+
+    if let Some((offset, c)) = line.char_indices().find(|(_, c)| c.is_whitespace()) {
+        return Err(ParseError::UnexpectedCharacter {
+            location: Location::of(idx, line, &line[offset..]),
+            token: c.to_string(),
+        });
+    }
+
+    for number in line.split(|c: char| !c.is_ascii_digit()) {
+        if !number.is_empty() && number.parse::<u32>().is_err() {
+            return Err(ParseError::InvalidNumber {
+                location: Location::of(idx, line, number),
+                token: number.to_owned(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// A number in the schematic, `len` digits long starting at `at`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartNumber {
+    pub value: u32,
+    pub at: Point,
+    pub len: usize,
+}
+
+impl PartNumber {
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let at = self.at;
+        (at.col..at.col + self.len).map(move |col| Point::new(at.row, col))
+    }
+}
+
+/// Anything that's neither a digit nor a `.`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub symbol: char,
+    pub at: Point,
+}
+
+/// The schematic as a grid, with its numbers and symbols picked out once up front.
+#[derive(Clone, Debug)]
+pub struct Schematic {
+    grid: Grid<char>,
+    numbers: Vec<PartNumber>,
+    symbols: Vec<Symbol>,
+    // Which number, if any, covers each cell.
+    owners: Grid<Option<usize>>,
+}
+
+impl Schematic {
+    fn new(grid: Grid<char>) -> Self {
+        let mut numbers = Vec::new();
+        let mut symbols = Vec::new();
+        let mut owners = grid.filled(None);
+
+        for (row, cells) in grid.rows().enumerate() {
+            let mut current: Option<PartNumber> = None;
+            for (col, &c) in cells.iter().enumerate() {
+                if let Some(digit) = c.to_digit(10) {
+                    let number = current.get_or_insert(PartNumber {
+                        value: 0,
+                        at: Point::new(row, col),
+                        len: 0,
+                    });
+                    number.value = number.value * 10 + digit;
+                    number.len += 1;
+                    continue;
                 }
-                _ => {
-                    if let Some(part) = part_number {
-                        for y in starting_part_number_coordinate..=last_part_number_coordinate {
-                            part_numbers.insert(
-                                (i, y),
-                                PartsCoordinate {
-                                    x: i,
-                                    y,
-                                    y_range: (
-                                        starting_part_number_coordinate,
-                                        last_part_number_coordinate,
-                                    ),
-                                    part_number: part,
-                                },
-                            );
-                        }
-                    }
-                    part_number = None;
-
-                    if c != '.' {
-                        symbol_coordinates.push((i, j));
-                    }
+                numbers.extend(current.take());
+                if c != '.' {
+                    symbols.push(Symbol {
+                        symbol: c,
+                        at: Point::new(row, col),
+                    });
                 }
             }
+            numbers.extend(current);
         }
 
-        if let Some(part) = part_number {
-            for y in starting_part_number_coordinate..=last_part_number_coordinate {
-                part_numbers.insert(
-                    (i, y),
-                    PartsCoordinate {
-                        x: i,
-                        y,
-                        y_range: (starting_part_number_coordinate, last_part_number_coordinate),
-                        part_number: part,
-                    },
-                );
+        for (idx, number) in numbers.iter().enumerate() {
+            for point in number.points() {
+                if let Some(owner) = owners.get_mut(point) {
+                    *owner = Some(idx);
+                }
             }
         }
+
+        Schematic {
+            grid,
+            numbers,
+            symbols,
+            owners,
+        }
+    }
+
+    pub fn grid(&self) -> &Grid<char> {
+        &self.grid
     }
 
-    let mut seen_numbers: HashSet<(usize, usize)> = HashSet::new();
-    for (i, j) in symbol_coordinates {
-        let neighbors = ((i - 1)..=(i + 1)).cartesian_product((j - 1)..=(j + 1));
+    pub fn numbers(&self) -> &[PartNumber] {
+        &self.numbers
+    }
 
-        for (x, y) in neighbors {
-            if seen_numbers.contains(&(x, y)) {
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn number_at(&self, point: Point) -> Option<&PartNumber> {
+        let idx = (*self.owners.get(point)?)?;
+        Some(&self.numbers[idx])
+    }
+
+    /// Every number with a digit in one of the eight cells around `point`, each listed once.
+    pub fn numbers_around(&self, point: Point) -> Vec<&PartNumber> {
+        let around = self.indices_around(point);
+        around.into_iter().map(|idx| &self.numbers[idx]).collect()
+    }
+
+    fn indices_around(&self, point: Point) -> Vec<usize> {
+        let mut around: Vec<usize> = self
+            .grid
+            .neighbors8(point)
+            .filter_map(|neighbor| self.owners[neighbor])
+            .collect();
+        around.sort_unstable();
+        around.dedup();
+        around
+    }
+
+    /// Works out which numbers and symbols touch each other under `rules`.
+    pub fn report(&self, rules: &AdjacencyRules) -> AdjacencyReport<'_> {
+        let mut symbols_of_number = vec![Vec::new(); self.numbers.len()];
+        let mut numbers_of_symbol = vec![Vec::new(); self.symbols.len()];
+        for (symbol_idx, symbol) in self.symbols.iter().enumerate() {
+            if !(rules.is_symbol)(symbol.symbol) {
                 continue;
             }
-            if let Some(part) = part_numbers.get(&(x, y)) {
-                adjacent_parts += part.part_number;
-                for l in part.y_range.0..=part.y_range.1 {
-                    seen_numbers.insert((x, l));
-                }
+            let around = self.indices_around(symbol.at);
+            for &number_idx in &around {
+                symbols_of_number[number_idx].push(symbol_idx);
             }
+            numbers_of_symbol[symbol_idx] = around;
         }
-    }
-    adjacent_parts
-}
-
-pub fn sum_of_gear_ratios(input: &str) -> u32 {
-    let mut adjacent_parts = 0;
-
-    let mut symbol_coordinates: Vec<(usize, usize)> = Vec::new();
-    let mut part_numbers: HashMap<(usize, usize), PartsCoordinate> = HashMap::new();
-
-    for (i, line) in input.lines().enumerate() {
-        let mut part_number = None;
-        let mut starting_part_number_coordinate = 0;
-        let mut last_part_number_coordinate = 0;
-        for (j, c) in line.chars().enumerate() {
-            match c {
-                '0'..='9' => {
-                    if let Some(number) = c.to_digit(10) {
-                        if let Some(part) = part_number {
-                            part_number = Some((part * 10) + number);
-                            last_part_number_coordinate = j;
-                        } else {
-                            starting_part_number_coordinate = j;
-                            last_part_number_coordinate = j;
-                            part_number = Some(number);
-                        }
-                    }
-                }
-                _ => {
-                    if let Some(part) = part_number {
-                        for y in starting_part_number_coordinate..=last_part_number_coordinate {
-                            part_numbers.insert(
-                                (i, y),
-                                PartsCoordinate {
-                                    x: i,
-                                    y,
-                                    y_range: (
-                                        starting_part_number_coordinate,
-                                        last_part_number_coordinate,
-                                    ),
-                                    part_number: part,
-                                },
-                            );
-                        }
-                    }
-                    part_number = None;
-
-                    if c == '*' {
-                        symbol_coordinates.push((i, j));
-                    }
-                }
-            }
+
+        AdjacencyReport {
+            schematic: self,
+            gear: rules.gear,
+            symbols_of_number,
+            numbers_of_symbol,
         }
+    }
+}
 
-        if let Some(part) = part_number {
-            for y in starting_part_number_coordinate..=last_part_number_coordinate {
-                part_numbers.insert(
-                    (i, y),
-                    PartsCoordinate {
-                        x: i,
-                        y,
-                        y_range: (starting_part_number_coordinate, last_part_number_coordinate),
-                        part_number: part,
-                    },
-                );
-            }
+/// Which characters count as symbols, and which symbol with how many numbers around it is a gear.
+pub struct AdjacencyRules {
+    is_symbol: Box<dyn Fn(char) -> bool>,
+    gear: (char, usize),
+}
+
+/// The puzzle's rules: anything but a digit or `.` is a symbol, and a gear is a `*` between
+/// exactly two numbers.
+impl Default for AdjacencyRules {
+    fn default() -> Self {
+        Self {
+            is_symbol: Box::new(|c| c != '.' && !c.is_ascii_digit()),
+            gear: ('*', 2),
         }
     }
+}
 
-    let mut seen_numbers: HashSet<(usize, usize)> = HashSet::new();
-    for (i, j) in symbol_coordinates {
-        let neighbors = ((i - 1)..=(i + 1)).cartesian_product((j - 1)..=(j + 1));
-        let mut seen_part_numbers: Vec<u32> = Vec::new();
+impl AdjacencyRules {
+    pub fn symbols(mut self, is_symbol: impl Fn(char) -> bool + 'static) -> Self {
+        self.is_symbol = Box::new(is_symbol);
+        self
+    }
 
-        for (x, y) in neighbors {
-            if seen_numbers.contains(&(x, y)) {
-                continue;
-            }
-            if let Some(part) = part_numbers.get(&(x, y)) {
-                seen_part_numbers.push(part.part_number);
+    pub fn gear(mut self, symbol: char, numbers: usize) -> Self {
+        self.gear = (symbol, numbers);
+        self
+    }
+}
 
-                for l in part.y_range.0..=part.y_range.1 {
-                    seen_numbers.insert((x, l));
-                }
-            }
-        }
-        if seen_part_numbers.len() == 2 {
-            adjacent_parts += seen_part_numbers.iter().product::<u32>();
+/// Which numbers touch which symbols in a schematic. Numbers and symbols are looked up by their
+/// index in `Schematic::numbers` and `Schematic::symbols`.
+pub struct AdjacencyReport<'s> {
+    schematic: &'s Schematic,
+    gear: (char, usize),
+    symbols_of_number: Vec<Vec<usize>>,
+    numbers_of_symbol: Vec<Vec<usize>>,
+}
+
+impl<'s> AdjacencyReport<'s> {
+    pub fn symbols_touching(&self, number: usize) -> impl Iterator<Item = &'s Symbol> + '_ {
+        let symbols = &self.schematic.symbols;
+        self.symbols_of_number[number]
+            .iter()
+            .map(move |&idx| &symbols[idx])
+    }
+
+    pub fn numbers_touching(&self, symbol: usize) -> impl Iterator<Item = &'s PartNumber> + '_ {
+        let numbers = &self.schematic.numbers;
+        self.numbers_of_symbol[symbol]
+            .iter()
+            .map(move |&idx| &numbers[idx])
+    }
+
+    /// Numbers touching at least one symbol.
+    pub fn part_numbers(&self) -> impl Iterator<Item = &'s PartNumber> + '_ {
+        self.numbers_where(|symbols| !symbols.is_empty())
+    }
+
+    /// Numbers that don't touch any symbol.
+    pub fn orphans(&self) -> impl Iterator<Item = &'s PartNumber> + '_ {
+        self.numbers_where(|symbols| symbols.is_empty())
+    }
+
+    fn numbers_where(
+        &self,
+        keep: impl Fn(&[usize]) -> bool + 'static,
+    ) -> impl Iterator<Item = &'s PartNumber> + '_ {
+        let numbers = &self.schematic.numbers;
+        self.symbols_of_number
+            .iter()
+            .zip(numbers)
+            .filter(move |(symbols, _)| keep(symbols))
+            .map(|(_, number)| number)
+    }
+
+    /// Every gear along with the numbers around it.
+    pub fn gears(&self) -> impl Iterator<Item = (&'s Symbol, Vec<&'s PartNumber>)> + '_ {
+        let (gear, count) = self.gear;
+        self.schematic
+            .symbols
+            .iter()
+            .zip(&self.numbers_of_symbol)
+            .filter(move |(symbol, numbers)| symbol.symbol == gear && numbers.len() == count)
+            .map(|(symbol, numbers)| {
+                let numbers = numbers.iter().map(|&idx| &self.schematic.numbers[idx]);
+                (symbol, numbers.collect())
+            })
+    }
+}
+
+pub struct Day3;
+
+impl Solution for Day3 {
+    type Input<'a> = Schematic;
+    type Answer = u64;
+    type Error = ParseError;
+
+    fn parse(input: &str) -> Result<Self::Input<'_>, Self::Error> {
+        let lines: Vec<&str> = input.lines().collect();
+        let width = lines.first().map_or(0, |line| line.chars().count());
+        for (idx, line) in lines.iter().enumerate() {
+            validate_row(idx, line, width)?;
         }
+        let grid = Grid::from_rows(lines.iter().map(|line| line.chars()))
+            .expect("every row was checked to be the same width");
+        Ok(Schematic::new(grid))
     }
-    adjacent_parts
+
+    fn part_one(input: &Self::Input<'_>) -> Result<Self::Answer, Self::Error> {
+        Ok(sum_of_adjacent_parts(input))
+    }
+
+    fn part_two(input: &Self::Input<'_>) -> Option<Result<Self::Answer, Self::Error>> {
+        Some(sum_of_adjacent_gears(input))
+    }
+}
+
+pub fn sum_of_engine_parts(input: &str) -> Result<u64, ParseError> {
+    Ok(sum_of_adjacent_parts(&Day3::parse(input)?))
+}
+
+pub fn sum_of_gear_ratios(input: &str) -> Result<u64, ParseError> {
+    sum_of_adjacent_gears(&Day3::parse(input)?)
+}
+
+// Every part number takes up at least one cell of the grid, so their sum can't outgrow a `u64`.
+fn sum_of_adjacent_parts(schematic: &Schematic) -> u64 {
+    let report = schematic.report(&AdjacencyRules::default());
+    report
+        .part_numbers()
+        .map(|number| u64::from(number.value))
+        .sum()
+}
+
+fn sum_of_adjacent_gears(schematic: &Schematic) -> Result<u64, ParseError> {
+    let report = schematic.report(&AdjacencyRules::default());
+    let sum = report.gears().try_fold(0_u64, |sum, (_, numbers)| {
+        numbers
+            .iter()
+            .try_fold(1_u64, |ratio, number| {
+                ratio.checked_mul(number.value.into())
+            })
+            .ok_or(ParseError::Overflow("a gear ratio"))?
+            .checked_add(sum)
+            .ok_or(ParseError::Overflow("the sum of the gear ratios"))
+    });
+    sum
 }
 
 #[cfg(test)]
@@ -190,25 +340,103 @@ mod tests {
 
     #[test]
     fn sample_sum_of_engine_parts() {
-        let result = sum_of_engine_parts(SAMPLE);
+        let result = sum_of_engine_parts(SAMPLE).unwrap();
         assert_eq!(result, 4361);
     }
 
     #[test]
     fn input_sum_of_engine_parts() {
-        let result = sum_of_engine_parts(INPUT);
+        let result = sum_of_engine_parts(INPUT).unwrap();
         assert_eq!(result, 525119);
     }
 
     #[test]
     fn sample_sum_of_gear_ratios() {
-        let result = sum_of_gear_ratios(SAMPLE);
+        let result = sum_of_gear_ratios(SAMPLE).unwrap();
         assert_eq!(result, 467835);
     }
 
     #[test]
     fn input_sum_of_gear_ratios() {
-        let result = sum_of_gear_ratios(INPUT);
+        let result = sum_of_gear_ratios(INPUT).unwrap();
         assert_eq!(result, 76504829);
     }
+
+    #[test]
+    fn symbols_on_the_edge() {
+        let schematic = Day3::parse("*12\n3..\n..#").unwrap();
+        assert_eq!(schematic.numbers().len(), 2);
+        assert_eq!(schematic.symbols().len(), 2);
+        assert_eq!(
+            schematic.number_at(Point::new(0, 2)),
+            Some(&PartNumber {
+                value: 12,
+                at: Point::new(0, 1),
+                len: 2,
+            })
+        );
+
+        let around: Vec<u32> = schematic
+            .numbers_around(Point::new(0, 0))
+            .iter()
+            .map(|number| number.value)
+            .collect();
+        assert_eq!(around, [12, 3]);
+        assert_eq!(sum_of_engine_parts("*12\n3..\n..#"), Ok(15));
+        assert_eq!(sum_of_gear_ratios("*12\n3..\n..#"), Ok(36));
+        assert_eq!(sum_of_gear_ratios("99999*99999"), Ok(99999 * 99999));
+    }
+
+    #[test]
+    fn adjacency_report() {
+        let schematic = Day3::parse(SAMPLE).unwrap();
+        let values = |numbers: Vec<&PartNumber>| -> Vec<u32> {
+            numbers.into_iter().map(|number| number.value).collect()
+        };
+
+        let report = schematic.report(&AdjacencyRules::default());
+        assert_eq!(values(report.orphans().collect()), [114, 58]);
+        let symbols: Vec<char> = report.symbols_touching(0).map(|s| s.symbol).collect();
+        assert_eq!(symbols, ['*']);
+        assert_eq!(values(report.numbers_touching(2).collect()), [617]);
+        let gears: Vec<(Point, Vec<u32>)> = report
+            .gears()
+            .map(|(symbol, numbers)| (symbol.at, values(numbers)))
+            .collect();
+        assert_eq!(
+            gears,
+            [
+                (Point::new(1, 3), vec![467, 35]),
+                (Point::new(8, 5), vec![755, 598]),
+            ]
+        );
+
+        let rules = AdjacencyRules::default().symbols(|c| c == '*').gear('*', 1);
+        let report = schematic.report(&rules);
+        assert_eq!(values(report.orphans().collect()), [114, 633, 58, 592, 664]);
+        assert_eq!(report.gears().count(), 1);
+        assert_eq!(report.numbers_touching(1).count(), 0);
+    }
+
+    #[test]
+    fn malformed_schematic() {
+        let result = sum_of_engine_parts("467..\n...*.\n..35");
+        assert_eq!(
+            result,
+            Err(ParseError::UnevenRow {
+                location: Location::new(3, 1),
+                expected: 5,
+                token: "..35".to_owned(),
+            })
+        );
+
+        let result = sum_of_gear_ratios("467..\n.. *.");
+        assert_eq!(
+            result,
+            Err(ParseError::UnexpectedCharacter {
+                location: Location::new(2, 3),
+                token: " ".to_owned(),
+            })
+        );
+    }
 }
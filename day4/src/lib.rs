@@ -1,94 +1,230 @@
+use aoc_core::{Location, Solution};
 use std::collections::{HashMap, HashSet};
 
-pub fn scratch_card_point(input: &str) -> u32 {
-    let mut points = 0;
-    for line in input.lines() {
-        let mut midpoint = line.split(" | ");
-        let mut winner_cards: HashSet<u32> = HashSet::new();
-        if let Some(winner_card) = midpoint.next() {
-            if let Some(cards) = winner_card.split(": ").nth(1) {
-                let cards = cards
-                    .split(" ")
-                    .filter_map(|card| u32::from_str_radix(card, 10).ok());
-                winner_cards = cards.collect();
-            }
-        }
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("{location}: expected `Card <id>: ` but found `{token}`")]
+    MissingColon { location: Location, token: String },
+    #[error("{location}: invalid card id `{token}`")]
+    InvalidCardId { location: Location, token: String },
+    #[error("{location}: `{token}` appears more than once")]
+    DuplicateCardId { location: Location, token: String },
+    #[error("{location}: expected ` | ` between the two sets of numbers in `{token}`")]
+    MissingSeparator { location: Location, token: String },
+    #[error("{location}: invalid number `{token}`")]
+    InvalidNumber { location: Location, token: String },
+    #[error("{location}: `{token}` is listed twice on the same side of the card")]
+    DuplicateNumber { location: Location, token: String },
+    #[error("{0} doesn't fit in a u64")]
+    Overflow(&'static str),
+}
 
-        if let Some(cards) = midpoint.next() {
-            let mut winners = 0;
-            for card in cards.split(" ") {
-                if let Ok(card_value) = u32::from_str_radix(card, 10) {
-                    if winner_cards.contains(&card_value) {
-                        winners += 1;
-                    }
-                }
-            }
-            if winners > 0 {
-                points += 2_u32.pow(winners - 1)
-            }
-        }
-    }
-    points
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Card {
+    pub id: u32,
+    pub winning: HashSet<u32>,
+    pub have: Vec<u32>,
 }
 
-#[derive(Clone, Debug)]
-struct ScratchCard {
-    index: usize,
-    cards: Vec<u32>,
+impl Card {
+    /// How many of the numbers we have are winning numbers.
+    pub fn matches(&self) -> usize {
+        self.have
+            .iter()
+            .filter(|number| self.winning.contains(number))
+            .count()
+    }
+
+    pub fn score(&self, rule: &impl ScoringRule) -> u64 {
+        rule.score(self.matches())
+    }
 }
 
-pub fn scratch_card_duplication(input: &str) -> u32 {
-    let mut all_winner_cards: HashMap<usize, HashSet<u32>> = HashMap::new();
-    let mut original_cards: Vec<ScratchCard> = Vec::new();
+/// Turns a card's number of matches into points.
+pub trait ScoringRule {
+    fn score(&self, matches: usize) -> u64;
+}
 
-    for (idx, line) in input.lines().enumerate() {
-        let mut midpoint = line.split(" | ");
-        let mut winner_cards: HashSet<u32> = HashSet::new();
+/// The puzzle's rule: one point for the first match, doubled for every match after it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Doubling;
 
-        if let Some(winner_card) = midpoint.next() {
-            if let Some(cards) = winner_card.split(": ").nth(1) {
-                let cards = cards
-                    .split(" ")
-                    .filter_map(|card| u32::from_str_radix(card, 10).ok());
-                winner_cards = cards.collect();
-            }
-        }
-        all_winner_cards.insert(idx, winner_cards);
-
-        if let Some(cards) = midpoint.next() {
-            let parsed_cards: Vec<u32> = cards
-                .split(" ")
-                .filter_map(|card| u32::from_str_radix(card, 10).ok())
-                .collect();
-            original_cards.push(ScratchCard {
-                index: idx,
-                cards: parsed_cards,
-            });
+impl ScoringRule for Doubling {
+    fn score(&self, matches: usize) -> u64 {
+        match matches {
+            0 => 0,
+            matches => 1_u64.checked_shl(matches as u32 - 1).unwrap_or(u64::MAX),
         }
     }
+}
 
-    let original_cards_duplicate = original_cards.clone();
+/// One point per match.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Linear;
 
-    let mut total_cards = 0;
-    while let Some(card) = original_cards.pop() {
-        total_cards += 1;
-        let mut winners = 0;
+impl ScoringRule for Linear {
+    fn score(&self, matches: usize) -> u64 {
+        matches as u64
+    }
+}
 
-        if let Some(winning_cards) = all_winner_cards.get(&card.index) {
-            for c in card.cards {
-                if winning_cards.contains(&c) {
-                    winners += 1;
-                }
-            }
+/// The `n`th Fibonacci number for `n` matches: 1, 1, 2, 3, 5, ...
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Fibonacci;
+
+impl ScoringRule for Fibonacci {
+    fn score(&self, matches: usize) -> u64 {
+        let (mut current, mut next) = (0_u64, 1_u64);
+        for _ in 0..matches {
+            (current, next) = (next, current.saturating_add(next));
         }
-        for winner in 1..=winners {
-            let duplicate_card_idx = card.index + winner;
-            if let Some(card_clone) = original_cards_duplicate.get(duplicate_card_idx) {
-                original_cards.push(card_clone.clone());
+        current
+    }
+}
+
+impl<F: Fn(usize) -> u64> ScoringRule for F {
+    fn score(&self, matches: usize) -> u64 {
+        self(matches)
+    }
+}
+
+fn parse_numbers(idx: usize, line: &str, numbers: &str) -> Result<Vec<u32>, ParseError> {
+    let mut seen = HashSet::new();
+    numbers
+        .split_whitespace()
+        .map(|number| {
+            let value = number
+                .parse::<u32>()
+                .map_err(|_| ParseError::InvalidNumber {
+                    location: Location::of(idx, line, number),
+                    token: number.to_owned(),
+                })?;
+            if !seen.insert(value) {
+                return Err(ParseError::DuplicateNumber {
+                    location: Location::of(idx, line, number),
+                    token: number.to_owned(),
+                });
             }
+            Ok(value)
+        })
+        .collect()
+}
+
+fn parse_card(idx: usize, line: &str) -> Result<Card, ParseError> {
+    let Some((header, numbers)) = line.split_once(": ") else {
+        return Err(ParseError::MissingColon {
+            location: Location::of(idx, line, line),
+            token: line.to_owned(),
+        });
+    };
+    let Some((winning, have)) = numbers.split_once(" | ") else {
+        return Err(ParseError::MissingSeparator {
+            location: Location::of(idx, line, numbers),
+            token: numbers.to_owned(),
+        });
+    };
+
+    let id = header
+        .strip_prefix("Card")
+        .and_then(|id| id.trim_start().parse::<u32>().ok())
+        .ok_or_else(|| ParseError::InvalidCardId {
+            location: Location::of(idx, line, header),
+            token: header.to_owned(),
+        })?;
+
+    Ok(Card {
+        id,
+        winning: parse_numbers(idx, line, winning)?.into_iter().collect(),
+        have: parse_numbers(idx, line, have)?,
+    })
+}
+
+pub struct Day4;
+
+impl Solution for Day4 {
+    type Input<'a> = Vec<Card>;
+    type Answer = u64;
+    type Error = ParseError;
+
+    fn parse(input: &str) -> Result<Self::Input<'_>, Self::Error> {
+        let mut ids = HashSet::new();
+        input
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| {
+                let card = parse_card(idx, line)?;
+                if !ids.insert(card.id) {
+                    let header = line.split_once(':').map_or(line, |(header, _)| header);
+                    return Err(ParseError::DuplicateCardId {
+                        location: Location::of(idx, line, header),
+                        token: header.to_owned(),
+                    });
+                }
+                Ok(card)
+            })
+            .collect()
+    }
+
+    fn part_one(input: &Self::Input<'_>) -> Result<Self::Answer, Self::Error> {
+        Ok(sum_of_points(input, &Doubling))
+    }
+
+    fn part_two(input: &Self::Input<'_>) -> Option<Result<Self::Answer, Self::Error>> {
+        Some(copies_of_cards(input).map(|copies| copies.total))
+    }
+}
+
+pub fn scratch_card_point(input: &str, rule: &impl ScoringRule) -> Result<u64, ParseError> {
+    Ok(sum_of_points(&Day4::parse(input)?, rule))
+}
+
+pub fn scratch_card_duplication(input: &str) -> Result<u64, ParseError> {
+    Ok(copies_of_cards(&Day4::parse(input)?)?.total)
+}
+
+pub fn scratch_card_copies(input: &str) -> Result<Copies, ParseError> {
+    copies_of_cards(&Day4::parse(input)?)
+}
+
+fn sum_of_points(cards: &[Card], rule: &impl ScoringRule) -> u64 {
+    cards.iter().map(|card| card.score(rule)).sum()
+}
+
+/// How many of each card end up in the pile, the original included. `per_card` follows the order
+/// the cards were listed in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Copies {
+    pub total: u64,
+    pub per_card: Vec<u64>,
+}
+
+// Cards only ever win copies of the cards with the next few ids, so going through them by id, every
+// copy of a card has already been counted by the time it's reached. Ids that aren't in the pile
+// win nothing.
+fn copies_of_cards(cards: &[Card]) -> Result<Copies, ParseError> {
+    let positions: HashMap<u32, usize> = cards
+        .iter()
+        .enumerate()
+        .map(|(idx, card)| (card.id, idx))
+        .collect();
+    let mut by_id: Vec<usize> = (0..cards.len()).collect();
+    by_id.sort_unstable_by_key(|&idx| cards[idx].id);
+
+    let mut per_card = vec![1_u64; cards.len()];
+    for idx in by_id {
+        let card = &cards[idx];
+        let won = (1..=card.matches() as u32).filter_map(|offset| card.id.checked_add(offset));
+        for next in won.filter_map(|id| positions.get(&id)) {
+            per_card[*next] = per_card[*next]
+                .checked_add(per_card[idx])
+                .ok_or(ParseError::Overflow("the copies of a card"))?;
         }
     }
-    total_cards
+    let total = per_card
+        .iter()
+        .try_fold(0_u64, |total, &copies| total.checked_add(copies))
+        .ok_or(ParseError::Overflow("the total number of cards"))?;
+    Ok(Copies { total, per_card })
 }
 
 #[cfg(test)]
@@ -99,25 +235,122 @@ mod tests {
 
     #[test]
     fn sample_scratch_card_point() {
-        let result = scratch_card_point(SAMPLE);
+        let result = scratch_card_point(SAMPLE, &Doubling).unwrap();
         assert_eq!(result, 13);
     }
 
     #[test]
     fn sample_scratch_card_duplication() {
-        let result = scratch_card_duplication(SAMPLE);
+        let result = scratch_card_duplication(SAMPLE).unwrap();
         assert_eq!(result, 30);
     }
 
     #[test]
     fn input_scratch_card_point() {
-        let result = scratch_card_point(INPUT);
+        let result = scratch_card_point(INPUT, &Doubling).unwrap();
         assert_eq!(result, 18653);
     }
 
     #[test]
     fn input_scratch_card_duplication() {
-        let result = scratch_card_duplication(INPUT);
+        let result = scratch_card_duplication(INPUT).unwrap();
         assert_eq!(result, 5921508);
     }
+
+    #[test]
+    fn sample_copies_per_card() {
+        let result = scratch_card_copies(SAMPLE).unwrap();
+        assert_eq!(
+            result,
+            Copies {
+                total: 30,
+                per_card: vec![1, 2, 4, 8, 14, 1],
+            }
+        );
+
+        // Listed backwards, each card still ends up with the same number of copies.
+        let reversed: String = SAMPLE
+            .lines()
+            .rev()
+            .map(|line| format!("{line}\n"))
+            .collect();
+        let result = scratch_card_copies(&reversed).unwrap();
+        assert_eq!(result.per_card, [1, 14, 8, 4, 2, 1]);
+
+        // Card 2 is missing, so card 1 only wins a copy of card 3.
+        let result = scratch_card_copies("Card 1: 1 2 | 1 2\nCard 3: 5 | 6").unwrap();
+        assert_eq!(result.per_card, [1, 2]);
+
+        // Every card winning the next ten doubles the pile faster than a u64 can keep up with.
+        let deck: String = (1..=90)
+            .map(|id| format!("Card {id}: 1 2 3 4 5 6 7 8 9 10 | 1 2 3 4 5 6 7 8 9 10\n"))
+            .collect();
+        let result = scratch_card_duplication(&deck);
+        assert_eq!(result, Err(ParseError::Overflow("the copies of a card")));
+    }
+
+    #[test]
+    fn scoring_rules() {
+        let cards = Day4::parse(SAMPLE).unwrap();
+        let ids: Vec<u32> = cards.iter().map(|card| card.id).collect();
+        assert_eq!(ids, [1, 2, 3, 4, 5, 6]);
+        let matches: Vec<usize> = cards.iter().map(Card::matches).collect();
+        assert_eq!(matches, [4, 2, 2, 1, 0, 0]);
+
+        assert_eq!(sum_of_points(&cards, &Doubling), 8 + 2 + 2 + 1);
+        assert_eq!(sum_of_points(&cards, &Linear), 4 + 2 + 2 + 1);
+        assert_eq!(sum_of_points(&cards, &Fibonacci), 3 + 1 + 1 + 1);
+        assert_eq!(sum_of_points(&cards, &|matches| 10 * matches as u64), 90);
+
+        let card = Day4::parse("Card  17: 1 2 | 2 3").unwrap().remove(0);
+        assert_eq!(card.id, 17);
+    }
+
+    #[test]
+    fn malformed_cards() {
+        let result = scratch_card_point("Card 1: 41 48 | 83 86\nCard 2: 13 32 20 16 61", &Doubling);
+        assert_eq!(
+            result,
+            Err(ParseError::MissingSeparator {
+                location: Location::new(2, 9),
+                token: "13 32 20 16 61".to_owned(),
+            })
+        );
+
+        let result = scratch_card_duplication("Card 1: 41 4x | 83 86");
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidNumber {
+                location: Location::new(1, 12),
+                token: "4x".to_owned(),
+            })
+        );
+
+        let result = scratch_card_duplication("Card 1: 41 48 | 83 86 83");
+        assert_eq!(
+            result,
+            Err(ParseError::DuplicateNumber {
+                location: Location::new(1, 23),
+                token: "83".to_owned(),
+            })
+        );
+
+        let result = scratch_card_duplication("Cards: 41 48 | 83 86");
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidCardId {
+                location: Location::new(1, 1),
+                token: "Cards".to_owned(),
+            })
+        );
+
+        let result = scratch_card_duplication("Card 1: 41 | 83\nCard 1: 48 | 86");
+        assert_eq!(
+            result,
+            Err(ParseError::DuplicateCardId {
+                location: Location::new(2, 1),
+                token: "Card 1".to_owned(),
+            })
+        );
+    }
 }
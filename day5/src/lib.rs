@@ -1,110 +1,377 @@
-use anyhow::Result;
-use itertools::*;
+use aoc_core::{Location, Solution};
 use rayon::prelude::*;
 
-#[derive(Debug)]
-struct ConversionMap {
-    source_start: u64,
-    destination_start: u64,
-    range: u64,
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("{location}: invalid number `{token}`")]
+    InvalidNumber { location: Location, token: String },
+    #[error("{location}: expected exactly three numbers but found `{token}`")]
+    WrongArity { location: Location, token: String },
+    #[error("{location}: expected `<source>-to-<destination> map:` but found `{token}`")]
+    MalformedHeader { location: Location, token: String },
+    #[error("{location}: `{token}` appears before any `map:` header")]
+    MissingHeader { location: Location, token: String },
+    #[error("{location}: the conversion `{token}` runs past the largest number")]
+    RangeOverflow { location: Location, token: String },
+    #[error("the almanac doesn't list any `seeds:`")]
+    MissingSeeds,
+}
+
+fn parse_numbers(idx: usize, line: &str, numbers: &str) -> Result<Vec<u64>, ParseError> {
+    numbers
+        .split_whitespace()
+        .map(|number| {
+            number
+                .parse::<u64>()
+                .map_err(|_| ParseError::InvalidNumber {
+                    location: Location::of(idx, line, number),
+                    token: number.to_owned(),
+                })
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConversionMap {
+    pub source_start: u64,
+    pub destination_start: u64,
+    pub range: u64,
 }
 
 impl ConversionMap {
-    fn from_str(input: &str) -> Result<Self> {
-        let mut numbers = Vec::new();
+    fn from_str(idx: usize, input: &str) -> Result<Self, ParseError> {
+        let numbers = parse_numbers(idx, input, input)?;
 
-        for number in input.split(" ") {
-            let value = u64::from_str_radix(number, 10)?;
-            numbers.push(value);
+        if numbers.len() != 3 {
+            return Err(ParseError::WrongArity {
+                location: Location::of(idx, input, input),
+                token: input.to_owned(),
+            });
         }
 
-        if numbers.len() != 3 {
-            anyhow::bail!("Expected exactly three numbers.");
+        // With both ends in range, shifting a value through the map can't overflow.
+        let [destination_start, source_start, range] = [numbers[0], numbers[1], numbers[2]];
+        if source_start.checked_add(range).is_none()
+            || destination_start.checked_add(range).is_none()
+        {
+            return Err(ParseError::RangeOverflow {
+                location: Location::of(idx, input, input),
+                token: input.to_owned(),
+            });
         }
 
         Ok(ConversionMap {
-            source_start: *numbers.get(1).unwrap(),
-            destination_start: *numbers.get(0).unwrap(),
-            range: *numbers.get(2).unwrap(),
+            source_start,
+            destination_start,
+            range,
         })
     }
+
+    fn source_end(&self) -> u64 {
+        self.source_start.saturating_add(self.range)
+    }
+
+    fn destination_end(&self) -> u64 {
+        self.destination_start.saturating_add(self.range)
+    }
 }
 
-fn parse_seeds(input: &str) -> Vec<u64> {
-    let mut seeds = Vec::new();
-    for value in input.split(" ") {
-        if let Ok(seed_value) = u64::from_str_radix(value, 10) {
-            seeds.push(seed_value);
+/// One `<source>-to-<destination> map:` block. Values outside every conversion map to themselves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stage {
+    source: String,
+    destination: String,
+    conversions: Vec<ConversionMap>,
+}
+
+impl Stage {
+    fn from_header(idx: usize, line: &str) -> Result<Self, ParseError> {
+        let names = line
+            .strip_suffix(" map:")
+            .and_then(|names| names.split_once("-to-"));
+        let Some((source, destination)) = names else {
+            return Err(ParseError::MalformedHeader {
+                location: Location::of(idx, line, line),
+                token: line.to_owned(),
+            });
+        };
+        Ok(Stage {
+            source: source.to_owned(),
+            destination: destination.to_owned(),
+            conversions: Vec::new(),
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    pub fn conversions(&self) -> &[ConversionMap] {
+        &self.conversions
+    }
+
+    pub fn map(&self, value: u64) -> u64 {
+        for conversion in &self.conversions {
+            if value >= conversion.source_start && value < conversion.source_end() {
+                return conversion.destination_start + (value - conversion.source_start);
+            }
         }
+        value
+    }
+
+    /// Returns the smallest source value that maps onto `value`, if any does.
+    pub fn inverse(&self, value: u64) -> Option<u64> {
+        let shifted = self.conversions.iter().filter_map(|conversion| {
+            if value >= conversion.destination_start && value < conversion.destination_end() {
+                return Some(conversion.source_start + (value - conversion.destination_start));
+            }
+            None
+        });
+        std::iter::once(value)
+            .chain(shifted)
+            .filter(|&source| self.map(source) == value)
+            .min()
+    }
+
+    /// Flattens this stage followed by `next` into a single piecewise map, as long as `next`
+    /// picks up where this stage leaves off.
+    pub fn then(&self, next: &Stage) -> Option<Stage> {
+        if self.destination != next.source {
+            return None;
+        }
+
+        // Both stages are shifts between their boundaries, so their composition is a shift between
+        // this stage's boundaries and the points this stage sends onto `next`'s boundaries.
+        let mut boundaries = vec![0];
+        for conversion in &self.conversions {
+            boundaries.push(conversion.source_start);
+            boundaries.push(conversion.source_end());
+        }
+        for conversion in &next.conversions {
+            for boundary in [conversion.source_start, conversion.source_end()] {
+                boundaries.push(boundary);
+                for shifted in &self.conversions {
+                    if boundary >= shifted.destination_start && boundary < shifted.destination_end()
+                    {
+                        boundaries
+                            .push(shifted.source_start + (boundary - shifted.destination_start));
+                    }
+                }
+            }
+        }
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let mut conversions: Vec<ConversionMap> = Vec::new();
+        let ends = boundaries.iter().skip(1).copied().chain([u64::MAX]);
+        for (start, end) in boundaries.iter().copied().zip(ends) {
+            let destination_start = next.map(self.map(start));
+            if destination_start == start || start == end {
+                continue;
+            }
+            match conversions.last_mut() {
+                Some(last)
+                    if last.source_end() == start
+                        && last.destination_end() == destination_start =>
+                {
+                    last.range += end - start;
+                }
+                _ => conversions.push(ConversionMap {
+                    source_start: start,
+                    destination_start,
+                    range: end - start,
+                }),
+            }
+        }
+
+        Some(Stage {
+            source: self.source.clone(),
+            destination: next.destination.clone(),
+            conversions,
+        })
     }
-    seeds
 }
 
-fn part_two_parse_seeds_range(input: &str) -> Vec<u64> {
-    let mut seeds = Vec::new();
-    for value in input.split(" ") {
-        if let Ok(seed_value) = u64::from_str_radix(value, 10) {
-            seeds.push(seed_value);
+#[derive(Clone, Debug)]
+pub struct Almanac {
+    seeds: Vec<u64>,
+    stages: Vec<Stage>,
+}
+
+impl Almanac {
+    pub fn seeds(&self) -> &[u64] {
+        &self.seeds
+    }
+
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    pub fn categories(&self) -> impl Iterator<Item = &str> {
+        let first = self.stages.first().map(|stage| stage.source());
+        first
+            .into_iter()
+            .chain(self.stages.iter().map(|stage| stage.destination()))
+    }
+
+    // The run of stages leading from `from` to `to`, following the order of the almanac.
+    fn path(&self, from: &str, to: &str) -> Option<&[Stage]> {
+        let start = self.stages.iter().position(|stage| stage.source == from)?;
+        let mut category = from;
+        for (end, stage) in self.stages.iter().enumerate().skip(start) {
+            if stage.source != category {
+                return None;
+            }
+            category = &stage.destination;
+            if category == to {
+                return Some(&self.stages[start..=end]);
+            }
         }
+        None
     }
-    let mut final_seeds = Vec::new();
-    let mut seeds = seeds.into_iter();
-    while let Some((x, y)) = seeds.next_tuple() {
-        for k in x..x+y {
-            final_seeds.push(k);
+
+    /// Maps a value between any two categories, running the stages backwards when `to` comes
+    /// before `from`.
+    pub fn convert(&self, value: u64, from: &str, to: &str) -> Option<u64> {
+        if from == to {
+            return self.categories().any(|c| c == from).then_some(value);
+        }
+        if let Some(path) = self.path(from, to) {
+            return Some(path.iter().fold(value, |value, stage| stage.map(value)));
         }
+        let path = self.path(to, from)?;
+        path.iter()
+            .rev()
+            .try_fold(value, |value, stage| stage.inverse(value))
+    }
+
+    /// Flattens every stage between two categories into a single stage.
+    pub fn compose(&self, from: &str, to: &str) -> Option<Stage> {
+        let path = self.path(from, to)?;
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(first.clone(), |composed, stage| composed.then(stage))
     }
-    final_seeds
 }
 
-fn find_location_of_seed(seed: u64, conversion_map: &Vec<Vec<ConversionMap>>) -> u64 {
-    let mut current_val = seed;
-    for types in conversion_map {
-        'outer: for conversion in types {
-            let source_start = conversion.source_start;
-            let source_end = conversion.source_start + conversion.range;
-            if current_val >= source_start && current_val < source_end {
-                let destination_start = conversion.destination_start;
-                let destination_end = conversion.destination_start + conversion.range;
-                let next = conversion.destination_start + (current_val - conversion.source_start);
+pub struct Day5;
 
-                if next >= destination_start && next < destination_end {
-                    current_val = next;
-                    break 'outer;
+impl Solution for Day5 {
+    type Input<'a> = Almanac;
+    type Answer = u64;
+    type Error = ParseError;
+
+    fn parse(input: &str) -> Result<Self::Input<'_>, Self::Error> {
+        let mut seeds = None;
+        let mut stages: Vec<Stage> = Vec::new();
+        for (idx, line) in input.lines().enumerate() {
+            match line {
+                line if line.starts_with("seeds:") => {
+                    seeds = Some(parse_numbers(idx, line, &line["seeds:".len()..])?);
+                }
+                line if line.contains("map:") => {
+                    stages.push(Stage::from_header(idx, line)?);
+                }
+                line if line.trim().is_empty() => (),
+                _ => {
+                    let Some(stage) = stages.last_mut() else {
+                        return Err(ParseError::MissingHeader {
+                            location: Location::of(idx, line, line),
+                            token: line.to_owned(),
+                        });
+                    };
+                    stage.conversions.push(ConversionMap::from_str(idx, line)?);
                 }
             }
         }
+        let seeds = seeds.ok_or(ParseError::MissingSeeds)?;
+        Ok(Almanac { seeds, stages })
+    }
+
+    fn part_one(input: &Self::Input<'_>) -> Result<Self::Answer, Self::Error> {
+        Ok(lowest_location(&input.seeds, &input.stages))
+    }
+
+    fn part_two(input: &Self::Input<'_>) -> Option<Result<Self::Answer, Self::Error>> {
+        Some(Ok(lowest_location_of_ranges(&input.seeds, &input.stages)))
     }
-    current_val
 }
 
-pub fn find_lowest_location(input: &str, part_two: bool) -> u64 {
-    let mut seeds = Vec::new();
-    let mut maps: Vec<Vec<ConversionMap>> = Vec::new();
-    for line in input.lines() {
-        match line {
-            line if line.contains("seeds:") => {
-                if part_two {
-                    seeds = part_two_parse_seeds_range(line);
-                } else {
-                    seeds = parse_seeds(line);
-                }
+// Seed ranges are kept as half-open `(start, end)` pairs.
+fn seed_ranges(seeds: &[u64]) -> Vec<(u64, u64)> {
+    seeds
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[0].saturating_add(pair[1])))
+        .filter(|(start, end)| start < end)
+        .collect()
+}
+
+// Pushes whole ranges through a layer of conversions. A range that only partially overlaps a
+// conversion is split, the overlap is shifted and the leftovers are checked against the rest.
+fn convert_ranges(mut ranges: Vec<(u64, u64)>, conversions: &[ConversionMap]) -> Vec<(u64, u64)> {
+    let mut converted = Vec::new();
+    'ranges: while let Some((start, end)) = ranges.pop() {
+        for conversion in conversions {
+            let source_end = conversion.source_end();
+            let overlap_start = start.max(conversion.source_start);
+            let overlap_end = end.min(source_end);
+            if overlap_start >= overlap_end {
+                continue;
             }
-            line if line.contains("map:") => {
-                maps.push(Vec::new());
+
+            let offset = overlap_start - conversion.source_start;
+            let destination_start = conversion.destination_start.saturating_add(offset);
+            converted.push((
+                destination_start,
+                destination_start.saturating_add(overlap_end - overlap_start),
+            ));
+            if start < overlap_start {
+                ranges.push((start, overlap_start));
             }
-            _ => {
-                if let Some(map) = maps.last_mut() {
-                    if let Ok(conversion_map) = ConversionMap::from_str(line) {
-                        map.push(conversion_map);
-                    }
-                }
+            if overlap_end < end {
+                ranges.push((overlap_end, end));
             }
+            continue 'ranges;
         }
+        converted.push((start, end));
     }
+    merge_ranges(converted)
+}
+
+// Keeps the number of ranges bounded by the number of map boundaries rather than letting
+// adjacent splits pile up.
+fn merge_ranges(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+fn lowest_location_of_ranges(seeds: &[u64], stages: &[Stage]) -> u64 {
+    let mut ranges = seed_ranges(seeds);
+    for stage in stages {
+        ranges = convert_ranges(ranges, &stage.conversions);
+    }
+    ranges.first().map_or(0, |(start, _)| *start)
+}
+
+fn find_location_of_seed(seed: u64, stages: &[Stage]) -> u64 {
+    stages.iter().fold(seed, |value, stage| stage.map(value))
+}
+
+fn lowest_location(seeds: &[u64], stages: &[Stage]) -> u64 {
     let location = seeds
         .par_iter()
-        .map(|seed| find_location_of_seed(*seed, &maps))
+        .map(|seed| find_location_of_seed(*seed, stages))
         .min();
     if let Some(location) = location {
         return location;
@@ -112,6 +379,14 @@ pub fn find_lowest_location(input: &str, part_two: bool) -> u64 {
     0
 }
 
+pub fn find_lowest_location(input: &str, part_two: bool) -> Result<u64, ParseError> {
+    let almanac = Day5::parse(input)?;
+    if part_two {
+        return Ok(lowest_location_of_ranges(&almanac.seeds, &almanac.stages));
+    }
+    Ok(lowest_location(&almanac.seeds, &almanac.stages))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,25 +396,120 @@ mod tests {
 
     #[test]
     fn sample_find_lowest_location() {
-        let result = find_lowest_location(SAMPLE, false);
+        let result = find_lowest_location(SAMPLE, false).unwrap();
         assert_eq!(result, 35);
     }
 
     #[test]
     fn sample_find_lowest_location_seed_range() {
-        let result = find_lowest_location(SAMPLE, true);
+        let result = find_lowest_location(SAMPLE, true).unwrap();
         assert_eq!(result, 46);
     }
 
     #[test]
     fn input_find_lowest_location() {
-        let result = find_lowest_location(INPUT, false);
+        let result = find_lowest_location(INPUT, false).unwrap();
         assert_eq!(result, 551761867);
     }
 
     #[test]
     fn input_find_lowest_location_seed_range() {
-        let result = find_lowest_location(INPUT, true);
+        let result = find_lowest_location(INPUT, true).unwrap();
         assert_eq!(result, 57451709);
     }
+
+    #[test]
+    fn seed_ranges_match_every_seed() {
+        let almanac = Day5::parse(SAMPLE).unwrap();
+        for pair in almanac.seeds.chunks_exact(2) {
+            let seeds: Vec<u64> = (pair[0]..pair[0] + pair[1]).collect();
+            assert_eq!(
+                lowest_location_of_ranges(pair, &almanac.stages),
+                lowest_location(&seeds, &almanac.stages)
+            );
+        }
+    }
+
+    #[test]
+    fn convert_between_categories() {
+        let almanac = Day5::parse(SAMPLE).unwrap();
+        assert_eq!(almanac.convert(79, "seed", "location"), Some(82));
+        assert_eq!(almanac.convert(81, "soil", "humidity"), Some(78));
+        assert_eq!(almanac.convert(82, "location", "seed"), Some(79));
+        assert_eq!(almanac.convert(78, "humidity", "soil"), Some(81));
+        assert_eq!(almanac.convert(5, "soil", "soil"), Some(5));
+        assert_eq!(almanac.convert(5, "seed", "moon"), None);
+
+        for seed in 0..120 {
+            let location = almanac.convert(seed, "seed", "location").unwrap();
+            assert_eq!(almanac.convert(location, "location", "seed"), Some(seed));
+        }
+    }
+
+    #[test]
+    fn compose_stages() {
+        let almanac = Day5::parse(SAMPLE).unwrap();
+        let flattened = almanac.compose("seed", "location").unwrap();
+        assert_eq!(
+            (flattened.source(), flattened.destination()),
+            ("seed", "location")
+        );
+        for seed in 0..120 {
+            assert_eq!(
+                Some(flattened.map(seed)),
+                almanac.convert(seed, "seed", "location")
+            );
+        }
+
+        let stages = almanac.stages();
+        let water = stages[1].then(&stages[2]).unwrap();
+        assert_eq!(water.destination(), "water");
+        assert_eq!(water.map(53), stages[2].map(stages[1].map(53)));
+        assert!(stages[2].then(&stages[1]).is_none());
+    }
+
+    #[test]
+    fn malformed_almanac() {
+        let result = find_lowest_location("seeds: 79 14\n\nseed-to-soil map:\n50 98\n", false);
+        assert_eq!(
+            result,
+            Err(ParseError::WrongArity {
+                location: Location::new(4, 1),
+                token: "50 98".to_owned(),
+            })
+        );
+
+        let result = find_lowest_location("seeds: 79 14\n\nseed to soil map:\n", false);
+        assert_eq!(
+            result,
+            Err(ParseError::MalformedHeader {
+                location: Location::new(3, 1),
+                token: "seed to soil map:".to_owned(),
+            })
+        );
+
+        let result = find_lowest_location("seeds: 79 1x\n", true);
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidNumber {
+                location: Location::new(1, 11),
+                token: "1x".to_owned(),
+            })
+        );
+
+        let result = find_lowest_location(
+            "seeds: 1
+
+seed-to-soil map:
+18446744073709551615 0 5",
+            false,
+        );
+        assert_eq!(
+            result,
+            Err(ParseError::RangeOverflow {
+                location: Location::new(4, 1),
+                token: "18446744073709551615 0 5".to_owned(),
+            })
+        );
+    }
 }
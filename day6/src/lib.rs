@@ -1,77 +1,252 @@
-fn calculate_possible_winning_races(time: u128, record: u128) -> u128 {
-    let mut combinations = 0;
+use aoc_core::{Location, Solution};
+use num_integer::{Integer, Roots};
+use num_traits::{CheckedAdd, CheckedMul, CheckedSub, FromPrimitive, Unsigned};
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
 
-    for hold in 1..time {
-        let remainder = time - hold;
-        if hold > record / remainder {
-            combinations += 1;
+#[cfg(feature = "bigint")]
+pub use num_bigint::BigUint;
+
+pub mod model;
+pub mod strategy;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("{location}: invalid number `{token}`")]
+    InvalidNumber { location: Location, token: String },
+    #[error("{location}: expected `Time:` or `Distance:` but found `{token}`")]
+    UnexpectedLine { location: Location, token: String },
+    #[error("the race sheet doesn't have a `{0}` line")]
+    MissingLine(&'static str),
+    #[error("{location}: expected {expected} distances but found `{token}`")]
+    MismatchedRaces {
+        location: Location,
+        expected: usize,
+        token: String,
+    },
+    #[error("{0} doesn't fit in the integer type the races are solved with")]
+    Overflow(&'static str),
+}
+
+/// The integer types races can be solved with: `u64`, `u128`, or `BigUint` with the `bigint`
+/// feature.
+pub trait RaceInt:
+    Unsigned
+    + Integer
+    + Roots
+    + CheckedAdd
+    + CheckedSub
+    + CheckedMul
+    + FromPrimitive
+    + FromStr
+    + Clone
+    + Debug
+    + Display
+{
+}
+
+impl<T> RaceInt for T where
+    T: Unsigned
+        + Integer
+        + Roots
+        + CheckedAdd
+        + CheckedSub
+        + CheckedMul
+        + FromPrimitive
+        + FromStr
+        + Clone
+        + Debug
+        + Display
+{
+}
+
+#[derive(Debug)]
+pub struct Races<T = u128> {
+    times: Vec<T>,
+    distances: Vec<T>,
+    // The digits of each line exactly as written, so leading zeros survive kerning.
+    time_digits: String,
+    distance_digits: String,
+}
+
+fn digits_of(numbers: &str) -> String {
+    numbers.chars().filter(char::is_ascii_digit).collect()
+}
+
+fn parse_numbers<T: RaceInt>(idx: usize, line: &str, numbers: &str) -> Result<Vec<T>, ParseError> {
+    numbers
+        .split_whitespace()
+        .map(|digit| {
+            digit.parse::<T>().map_err(|_| ParseError::InvalidNumber {
+                location: Location::of(idx, line, digit),
+                token: digit.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Parses a race sheet into whichever integer type the races should be solved with.
+pub fn parse_races<T: RaceInt>(input: &str) -> Result<Races<T>, ParseError> {
+    let mut times = None;
+    let mut distances = None;
+    let mut time_digits = String::new();
+    let mut distance_digits = String::new();
+    let mut distance_line = (0, "");
+    for (idx, line) in input.lines().enumerate() {
+        if let Some(numbers) = line.strip_prefix("Time:") {
+            times = Some(parse_numbers(idx, line, numbers)?);
+            time_digits = digits_of(numbers);
+        } else if let Some(numbers) = line.strip_prefix("Distance:") {
+            distances = Some(parse_numbers(idx, line, numbers)?);
+            distance_digits = digits_of(numbers);
+            distance_line = (idx, line);
+        } else if !line.trim().is_empty() {
+            return Err(ParseError::UnexpectedLine {
+                location: Location::of(idx, line, line),
+                token: line.to_owned(),
+            });
         }
     }
-    combinations
+    let times = times.ok_or(ParseError::MissingLine("Time:"))?;
+    let distances = distances.ok_or(ParseError::MissingLine("Distance:"))?;
+
+    if times.len() != distances.len() {
+        let (idx, line) = distance_line;
+        return Err(ParseError::MismatchedRaces {
+            location: Location::of(idx, line, line),
+            expected: times.len(),
+            token: line.to_owned(),
+        });
+    }
+    Ok(Races {
+        times,
+        distances,
+        time_digits,
+        distance_digits,
+    })
 }
 
-pub fn boat_race(input: &str) -> u128 {
-    let mut times = Vec::new();
-    let mut distances = Vec::new();
-    for line in input.lines() {
-        match line {
-            line if line.contains("Time") => {
-                times = line
-                    .split_whitespace()
-                    .filter_map(|digit| u128::from_str_radix(digit, 10).ok())
-                    .collect();
-            }
-            line if line.contains("Distance") => {
-                distances = line
-                    .split_whitespace()
-                    .filter_map(|digit| u128::from_str_radix(digit, 10).ok())
-                    .collect();
-            }
-            _ => (),
+impl<T: RaceInt> Races<T> {
+    /// Each race's time and record distance.
+    pub fn races(&self) -> impl Iterator<Item = (&T, &T)> {
+        self.times.iter().zip(&self.distances)
+    }
+
+    /// The number of ways to win each race multiplied together.
+    pub fn product_of_wins(&self) -> Result<T, ParseError> {
+        let mut product = T::one();
+        for (time, distance) in self.races() {
+            let wins = winning_holds(time, distance)?;
+            product = product
+                .checked_mul(&wins.count)
+                .ok_or(ParseError::Overflow("the product of the ways to win"))?;
         }
+        Ok(product)
+    }
+
+    /// The number of ways to win the one long race the sheet describes once the spaces between
+    /// the numbers are ignored.
+    pub fn kerned_wins(&self) -> Result<T, ParseError> {
+        let time = kerned(&self.time_digits).ok_or(ParseError::Overflow("the kerned time"))?;
+        let distance =
+            kerned(&self.distance_digits).ok_or(ParseError::Overflow("the kerned distance"))?;
+        Ok(winning_holds(&time, &distance)?.count)
     }
-    let mut race_possibilities = Vec::new();
-    for (time, distance) in times.into_iter().zip(distances) {
-        race_possibilities.push(calculate_possible_winning_races(time, distance));
+}
+
+pub struct Day6;
+
+impl Solution for Day6 {
+    type Input<'a> = Races;
+    type Answer = u128;
+    type Error = ParseError;
+
+    fn parse(input: &str) -> Result<Self::Input<'_>, Self::Error> {
+        parse_races(input)
     }
 
-    race_possibilities.iter().product()
+    fn part_one(input: &Self::Input<'_>) -> Result<Self::Answer, Self::Error> {
+        input.product_of_wins()
+    }
+
+    fn part_two(input: &Self::Input<'_>) -> Option<Result<Self::Answer, Self::Error>> {
+        Some(input.kerned_wins())
+    }
 }
 
-pub fn boat_race_part_two(input: &str) -> u128 {
-    let mut time: u128 = 0;
-    let mut distance: u128 = 0;
-    for line in input.lines() {
-        match line {
-            line if line.contains("Time") => {
-                for digit in line.chars().filter(|c| c.is_numeric()) {
-                    if let Some(digit) = digit.to_digit(10) {
-                        let digit: u128 = digit.into();
-                        if time == 0 {
-                            time = digit.into();
-                        } else {
-                            time = (time * 10) + digit;
-                        }
-                    }
-                }
-            }
-            line if line.contains("Distance") => {
-                for digit in line.chars().filter(|c| c.is_numeric()) {
-                    if let Some(digit) = digit.to_digit(10) {
-                        let digit: u128 = digit.into();
-                        if distance == 0 {
-                            distance = digit.into();
-                        } else {
-                            distance = (distance * 10) + digit;
-                        }
-                    }
-                }
-            }
-            _ => (),
-        }
+/// The hold times that beat a record, as an inclusive interval, and how many there are.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Wins<T = u128> {
+    pub count: T,
+    pub interval: Option<(T, T)>,
+}
+
+/// Holding for `hold` ms covers `hold * (time - hold)`, so the winning holds lie strictly between
+/// the roots of `hold² - time * hold + record`, at `(time ± √(time² - 4 * record)) / 2`.
+pub fn winning_holds<T: RaceInt>(time: &T, record: &T) -> Result<Wins<T>, ParseError> {
+    let small = |n| T::from_u32(n).expect("every integer type holds small constants");
+    // Anything too big for `T` is certainly further than the record, which fits.
+    let beats = |hold: &T| {
+        let distance = time
+            .checked_sub(hold)
+            .and_then(|rest| hold.checked_mul(&rest));
+        distance.is_none_or(|distance| &distance > record)
+    };
+    let none = Wins {
+        count: T::zero(),
+        interval: None,
+    };
+
+    let squared = time
+        .checked_mul(time)
+        .ok_or(ParseError::Overflow("the square of the race time"))?;
+    let four_records = record
+        .checked_mul(&small(4))
+        .ok_or(ParseError::Overflow("four times the record"))?;
+    let Some(discriminant) = squared.checked_sub(&four_records) else {
+        return Ok(none);
+    };
+
+    // The integer square root can put the lower root off by one either way, so nudge it onto the
+    // first winning hold. The distances are symmetric around `time / 2`, so the last winning hold
+    // mirrors the first.
+    let half = time.div_floor(&small(2));
+    let mut first = (time.clone() - discriminant.sqrt()).div_floor(&small(2));
+    while first <= half && !beats(&first) {
+        first = first + T::one();
+    }
+    while !first.is_zero() && beats(&(first.clone() - T::one())) {
+        first = first - T::one();
+    }
+    if first > half {
+        return Ok(none);
+    }
+    let last = time.clone() - first.clone();
+
+    Ok(Wins {
+        count: last.clone() - first.clone() + T::one(),
+        interval: Some((first, last)),
+    })
+}
+
+// The second half of the puzzle ignores the spaces between the numbers.
+fn kerned<T: RaceInt>(digits: &str) -> Option<T> {
+    let ten = T::from_u32(10)?;
+    let mut kerned = T::zero();
+    for digit in digits.chars().filter_map(|c| c.to_digit(10)) {
+        kerned = kerned
+            .checked_mul(&ten)?
+            .checked_add(&T::from_u32(digit)?)?;
     }
+    Some(kerned)
+}
 
-    calculate_possible_winning_races(time, distance)
+pub fn boat_race(input: &str) -> Result<u128, ParseError> {
+    Day6::parse(input)?.product_of_wins()
+}
+
+pub fn boat_race_part_two(input: &str) -> Result<u128, ParseError> {
+    Day6::parse(input)?.kerned_wins()
 }
 
 #[cfg(test)]
@@ -83,25 +258,115 @@ mod tests {
 
     #[test]
     fn sample_boat_race() {
-        let result = boat_race(SAMPLE);
+        let result = boat_race(SAMPLE).unwrap();
         assert_eq!(result, 288);
     }
 
     #[test]
     fn sample_boat_race_part_two() {
-        let result = boat_race_part_two(SAMPLE);
+        let result = boat_race_part_two(SAMPLE).unwrap();
         assert_eq!(result, 71503);
+
+        // Leading zeros are part of the kerned number: this is a 705 ms race.
+        let result = boat_race_part_two("Time: 7 05\nDistance: 9 08");
+        assert_eq!(result, Ok(winning_holds(&705_u128, &908).unwrap().count));
+        assert_ne!(result, boat_race_part_two("Time: 75\nDistance: 98"));
     }
 
     #[test]
     fn input_boat_race() {
-        let result = boat_race(INPUT);
+        let result = boat_race(INPUT).unwrap();
         assert_eq!(result, 1413720);
     }
 
     #[test]
     fn input_boat_race_part_two() {
-        let result = boat_race_part_two(INPUT);
+        let result = boat_race_part_two(INPUT).unwrap();
         assert_eq!(result, 30565288);
     }
+
+    fn brute_force(time: u128, record: u128) -> Vec<u128> {
+        (0..=time)
+            .filter(|hold| hold * (time - hold) > record)
+            .collect()
+    }
+
+    #[test]
+    fn winning_interval() {
+        assert_eq!(
+            winning_holds(&7_u128, &9),
+            Ok(Wins {
+                count: 4,
+                interval: Some((2, 5)),
+            })
+        );
+        // Exactly matching the record at 10 * 20 doesn't count.
+        assert_eq!(
+            winning_holds(&30_u64, &200).unwrap().interval,
+            Some((11, 19))
+        );
+        assert_eq!(winning_holds(&4_u64, &4).unwrap().count, 0);
+        assert_eq!(winning_holds(&0_u128, &0).unwrap().interval, None);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn matches_brute_force(time in 0_u128..2_000, record in 0_u128..1_100_000) {
+            let holds = brute_force(time, record);
+            let wins = winning_holds(&time, &record).unwrap();
+            proptest::prop_assert_eq!(wins.count, holds.len() as u128);
+            let interval = holds.first().zip(holds.last()).map(|(&a, &b)| (a, b));
+            proptest::prop_assert_eq!(wins.interval, interval);
+        }
+    }
+
+    // Two 20 digit times kern into a 40 digit one, past what a u128 can hold.
+    const HUGE: &str = "Time: 10000000000000000000 20000000000000000000\nDistance: 1 1";
+
+    #[test]
+    fn overflow_is_reported() {
+        let races = parse_races::<u128>(HUGE).unwrap();
+        assert_eq!(
+            races.kerned_wins(),
+            Err(ParseError::Overflow("the kerned time"))
+        );
+        // Fits in a u64, but its square doesn't.
+        let races = parse_races::<u64>("Time: 5000000000\nDistance: 1").unwrap();
+        assert_eq!(
+            races.product_of_wins(),
+            Err(ParseError::Overflow("the square of the race time"))
+        );
+        assert_eq!(
+            parse_races::<u64>(SAMPLE).unwrap().product_of_wins(),
+            Ok(288)
+        );
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn big_races() {
+        let races = parse_races::<BigUint>(HUGE).unwrap();
+        let expected = "1000000000000000000019999999999999999999".parse().unwrap();
+        assert_eq!(races.kerned_wins(), Ok(expected));
+        assert_eq!(
+            parse_races::<BigUint>(INPUT).unwrap().kerned_wins(),
+            Ok(BigUint::from(30565288_u32))
+        );
+    }
+
+    #[test]
+    fn malformed_races() {
+        let result = boat_race("Time:      7  15   30\nDistance:  9  40");
+        assert_eq!(
+            result,
+            Err(ParseError::MismatchedRaces {
+                location: Location::new(2, 1),
+                expected: 3,
+                token: "Distance:  9  40".to_owned(),
+            })
+        );
+
+        let result = boat_race_part_two("Time:      7  15   30");
+        assert_eq!(result, Err(ParseError::MissingLine("Distance:")));
+    }
 }
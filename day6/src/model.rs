@@ -0,0 +1,191 @@
+//! Other ways a boat could turn holding the button into distance, and a solver that works for any
+//! of them.
+
+use crate::winning_holds;
+
+/// How far a boat gets in a race.
+pub trait BoatModel {
+    /// The distance covered in a race of `time` ms after holding the button for `hold` ms.
+    fn distance(&self, hold: u64, time: u64) -> f64;
+
+    /// Every hold that beats `record`, as inclusive intervals in order. Unless the model knows
+    /// better this tries every hold, which takes O(`time`), and compares distances as `f64`, so
+    /// records past 2^53 aren't told apart exactly.
+    fn winning_holds(&self, time: u64, record: u64) -> Vec<(u64, u64)> {
+        scan(self, time, record)
+    }
+}
+
+/// Any `Fn(hold, time) -> distance` works as a model too.
+impl<F: Fn(u64, u64) -> f64> BoatModel for F {
+    fn distance(&self, hold: u64, time: u64) -> f64 {
+        self(hold, time)
+    }
+}
+
+fn scan<M: BoatModel + ?Sized>(model: &M, time: u64, record: u64) -> Vec<(u64, u64)> {
+    let mut intervals: Vec<(u64, u64)> = Vec::new();
+    for hold in 0..=time {
+        if model.distance(hold, time) <= record as f64 {
+            continue;
+        }
+        match intervals.last_mut() {
+            Some((_, last)) if *last + 1 == hold => *last = hold,
+            _ => intervals.push((hold, hold)),
+        }
+    }
+    intervals
+}
+
+/// The puzzle's boat: every ms of holding adds 1 mm/ms of speed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LinearCharge;
+
+impl BoatModel for LinearCharge {
+    fn distance(&self, hold: u64, time: u64) -> f64 {
+        hold as f64 * time.saturating_sub(hold) as f64
+    }
+
+    fn winning_holds(&self, time: u64, record: u64) -> Vec<(u64, u64)> {
+        // Squaring any `u64` time fits in a `u128`, and the holds themselves are never past `time`.
+        let wins = winning_holds(&u128::from(time), &u128::from(record))
+            .expect("a u64 race can't overflow u128");
+        let narrow = |hold| u64::try_from(hold).expect("a hold never outlasts the race");
+        wins.interval
+            .map(|(first, last)| (narrow(first), narrow(last)))
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Charges like `LinearCharge` until the speed reaches `cap`, after which holding only wastes
+/// time.
+#[derive(Clone, Copy, Debug)]
+pub struct CappedCharge {
+    pub cap: u64,
+}
+
+impl BoatModel for CappedCharge {
+    fn distance(&self, hold: u64, time: u64) -> f64 {
+        hold.min(self.cap) as f64 * time.saturating_sub(hold) as f64
+    }
+
+    // Up to the cap this is `LinearCharge`, and past it a line falling from `cap * (time - cap)`.
+    // Whenever a hold past the cap still wins, so does the cap itself, so the wins stay in one
+    // interval.
+    fn winning_holds(&self, time: u64, record: u64) -> Vec<(u64, u64)> {
+        let Some((first, last)) = LinearCharge
+            .winning_holds(time, record)
+            .into_iter()
+            .next()
+            .filter(|&(first, _)| first <= self.cap)
+        else {
+            return Vec::new();
+        };
+        // Past the cap a hold wins while `cap * (time - hold) > record`.
+        let capped = (record / self.cap.max(1))
+            .checked_add(1)
+            .and_then(|needed| time.checked_sub(needed))
+            .filter(|&end| end > self.cap);
+        let last = match capped {
+            Some(end) => end,
+            None => last.min(self.cap),
+        };
+        vec![(first, last)]
+    }
+}
+
+/// Speed grows with the square of the hold, `rate * hold²`.
+#[derive(Clone, Copy, Debug)]
+pub struct QuadraticAcceleration {
+    pub rate: f64,
+}
+
+impl BoatModel for QuadraticAcceleration {
+    fn distance(&self, hold: u64, time: u64) -> f64 {
+        let speed = self.rate * (hold as f64).powi(2);
+        speed * time.saturating_sub(hold) as f64
+    }
+}
+
+/// Charges like `LinearCharge`, but the boat loses `per_ms` of its speed for every ms it moves.
+#[derive(Clone, Copy, Debug)]
+pub struct Drag {
+    pub per_ms: f64,
+}
+
+impl BoatModel for Drag {
+    fn distance(&self, hold: u64, time: u64) -> f64 {
+        let moving = time.saturating_sub(hold) as f64;
+        let speed = hold as f64;
+        if self.per_ms <= 0.0 {
+            return speed * moving;
+        }
+        // The speed shrinks geometrically, so the distance is a geometric series.
+        let kept = 1.0 - self.per_ms.min(1.0);
+        speed * (1.0 - kept.powf(moving)) / self.per_ms.min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_charge_matches_scanning() {
+        let scanned = |hold, time| LinearCharge.distance(hold, time);
+        for (time, record) in [(7, 9), (15, 40), (30, 200), (4, 4), (0, 0)] {
+            assert_eq!(
+                LinearCharge.winning_holds(time, record),
+                scanned.winning_holds(time, record)
+            );
+        }
+        assert_eq!(LinearCharge.winning_holds(7, 9), [(2, 5)]);
+        assert_eq!(Drag { per_ms: 0.0 }.winning_holds(7, 9), [(2, 5)]);
+
+        // Far too many holds to try one by one.
+        assert_eq!(
+            LinearCharge.winning_holds(5_000_000_000, 1),
+            [(1, 4_999_999_999)]
+        );
+        assert_eq!(LinearCharge.winning_holds(u64::MAX, u64::MAX).len(), 1);
+    }
+
+    #[test]
+    fn capped_charge_matches_scanning() {
+        for cap in 0..12 {
+            let model = CappedCharge { cap };
+            let scanned = |hold, time| model.distance(hold, time);
+            for time in 0..20 {
+                for record in 0..60 {
+                    assert_eq!(
+                        model.winning_holds(time, record),
+                        scanned.winning_holds(time, record),
+                        "cap {cap}, time {time}, record {record}"
+                    );
+                }
+            }
+        }
+        let model = CappedCharge { cap: 1_000 };
+        assert_eq!(
+            model.winning_holds(5_000_000_000, 1_000_000),
+            [(1, 4_999_998_999)]
+        );
+    }
+
+    #[test]
+    fn other_models() {
+        assert_eq!(CappedCharge { cap: 3 }.winning_holds(7, 9), [(2, 3)]);
+        assert_eq!(
+            QuadraticAcceleration { rate: 1.0 }.winning_holds(7, 40),
+            [(4, 5)]
+        );
+        // Losing half its speed every ms, a boat never gets further than twice its starting speed.
+        let drag = Drag { per_ms: 0.5 };
+        assert_eq!(drag.distance(4, 7), 4.0 + 2.0 + 1.0);
+        assert_eq!(drag.winning_holds(7, 5), [(3, 6)]);
+
+        let sawtooth = |hold: u64, _| (hold % 3) as f64;
+        assert_eq!(sawtooth.winning_holds(7, 1), [(2, 2), (5, 5)]);
+    }
+}
@@ -0,0 +1,181 @@
+//! The best way to run each race on a sheet, and how the whole lineup comes out.
+
+use crate::{winning_holds, ParseError, RaceInt, Races, Wins};
+use std::fmt::Write;
+
+/// How to run one race: when to let go, how far that gets, and every hold that still wins.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RacePlan<T = u128> {
+    pub time: T,
+    pub record: T,
+    pub best_hold: T,
+    pub max_distance: T,
+    /// How far past the record the best hold gets, or `None` when the record can't be reached.
+    pub margin: Option<T>,
+    pub wins: Wins<T>,
+}
+
+/// A plan for every race on the sheet, plus the puzzle's answer: the ways to win multiplied
+/// together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lineup<T = u128> {
+    pub races: Vec<RacePlan<T>>,
+    pub ways_to_win: T,
+}
+
+fn plan_race<T: RaceInt>(time: &T, record: &T) -> Result<RacePlan<T>, ParseError> {
+    // The distance peaks halfway through the race.
+    let two = T::from_u32(2).expect("every integer type holds small constants");
+    let best_hold = time.div_floor(&two);
+    let max_distance = best_hold
+        .checked_mul(&(time.clone() - best_hold.clone()))
+        .ok_or(ParseError::Overflow("the longest distance"))?;
+    Ok(RacePlan {
+        time: time.clone(),
+        record: record.clone(),
+        best_hold,
+        // Only tying the record doesn't win, so no margin either.
+        margin: max_distance
+            .checked_sub(record)
+            .filter(|margin| !margin.is_zero()),
+        max_distance,
+        wins: winning_holds(time, record)?,
+    })
+}
+
+impl<T: RaceInt> Races<T> {
+    pub fn lineup(&self) -> Result<Lineup<T>, ParseError> {
+        let races = self
+            .races()
+            .map(|(time, record)| plan_race(time, record))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Lineup {
+            ways_to_win: self.product_of_wins()?,
+            races,
+        })
+    }
+}
+
+fn or_dash<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_owned(), |value| value.to_string())
+}
+
+fn or_null<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "null".to_owned(), |value| value.to_string())
+}
+
+impl<T: RaceInt> Lineup<T> {
+    pub fn table(&self) -> String {
+        let mut table = format!(
+            "{:>4}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}\n",
+            "race", "time", "record", "hold", "distance", "margin", "wins", "holds"
+        );
+        for (race, plan) in (1..).zip(&self.races) {
+            let holds = plan
+                .wins
+                .interval
+                .as_ref()
+                .map(|(first, last)| format!("{first}..={last}"));
+            let _ = writeln!(
+                table,
+                "{:>4}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}",
+                race,
+                plan.time,
+                plan.record,
+                plan.best_hold,
+                plan.max_distance,
+                or_dash(plan.margin.as_ref()),
+                plan.wins.count,
+                or_dash(holds),
+            );
+        }
+        let _ = writeln!(table, "ways to win: {}", self.ways_to_win);
+        table
+    }
+
+    /// Numbers are written out in full, so they stay exact even past what a `u64` can hold.
+    pub fn to_json(&self) -> String {
+        let races: Vec<String> = (1..)
+            .zip(&self.races)
+            .map(|(race, plan)| {
+                let interval = plan.wins.interval.as_ref();
+                let (first, last) = (interval.map(|(first, _)| first), interval.map(|(_, last)| last));
+                format!(
+                    r#"{{"race":{race},"time":{},"record":{},"best_hold":{},"max_distance":{},"margin":{},"wins":{},"first_win":{},"last_win":{}}}"#,
+                    plan.time,
+                    plan.record,
+                    plan.best_hold,
+                    plan.max_distance,
+                    or_null(plan.margin.as_ref()),
+                    plan.wins.count,
+                    or_null(first),
+                    or_null(last),
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"races":[{}],"ways_to_win":{}}}"#,
+            races.join(","),
+            self.ways_to_win
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_races;
+
+    #[test]
+    fn sample_lineup() {
+        let lineup = parse_races::<u64>(include_str!("sample.txt"))
+            .unwrap()
+            .lineup()
+            .unwrap();
+        assert_eq!(lineup.ways_to_win, 288);
+        assert_eq!(
+            lineup.races[2],
+            RacePlan {
+                time: 30,
+                record: 200,
+                best_hold: 15,
+                max_distance: 225,
+                margin: Some(25),
+                wins: Wins {
+                    count: 9,
+                    interval: Some((11, 19)),
+                },
+            }
+        );
+
+        assert_eq!(
+            lineup.table().lines().nth(1).unwrap(),
+            "   1           7           9           3          12           3           4       2..=5"
+        );
+        assert!(lineup.to_json().starts_with(
+            r#"{"races":[{"race":1,"time":7,"record":9,"best_hold":3,"max_distance":12,"margin":3,"wins":4,"first_win":2,"last_win":5},"#
+        ));
+        assert!(lineup.to_json().ends_with(r#"}],"ways_to_win":288}"#));
+    }
+
+    #[test]
+    fn unbeatable_record() {
+        let lineup = parse_races::<u64>("Time: 4\nDistance: 5")
+            .unwrap()
+            .lineup()
+            .unwrap();
+        assert_eq!(lineup.races[0].margin, None);
+        assert!(lineup.table().contains("  -  "));
+        assert_eq!(
+            lineup.to_json(),
+            r#"{"races":[{"race":1,"time":4,"record":5,"best_hold":2,"max_distance":4,"margin":null,"wins":0,"first_win":null,"last_win":null}],"ways_to_win":0}"#
+        );
+
+        let tied = parse_races::<u64>("Time: 4\nDistance: 4")
+            .unwrap()
+            .lineup()
+            .unwrap();
+        assert_eq!(tied.races[0].margin, None);
+        assert_eq!(tied.races[0].wins.interval, None);
+    }
+}
@@ -1,161 +1,261 @@
+use aoc_core::{Location, Solution};
 use std::collections::HashMap;
 
+pub mod poker;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("{location}: expected a hand of five cards but found `{token}`")]
+    WrongHandSize { location: Location, token: String },
+    #[error("{location}: unexpected card `{token}`")]
+    InvalidCard { location: Location, token: String },
+    #[error("{location}: expected a bid after the hand in `{token}`")]
+    MissingBid { location: Location, token: String },
+    #[error("{location}: invalid bid `{token}`")]
+    InvalidBid { location: Location, token: String },
+    #[error("{location}: expected five to seven cards but found `{token}`")]
+    WrongDealSize { location: Location, token: String },
+    #[error("{location}: `{token}` is dealt more than once")]
+    DuplicateCard { location: Location, token: String },
+    #[error("{0} doesn't fit in a u64")]
+    Overflow(&'static str),
+}
+
 #[derive(Debug, PartialEq, Eq)]
-struct Hand {
-    card: String,
-    kind: HandType,
+pub struct Hand {
+    cards: String,
     bet: u32,
+    key: u64,
 }
 
 impl Hand {
-    fn new(card: String, kind: HandType, bet: u32) -> Self {
-        Self{ card, kind, bet}
+    fn new(cards: String, bet: u32) -> Self {
+        let key = sort_key(&cards, &Standard);
+        Self { cards, bet, key }
     }
-}
 
-#[derive(Debug, PartialEq, Eq,  PartialOrd)]
-enum HandType {
-  HighCard,
-  OnePair,
-  TwoPair,
-  ThreeOfAKind,
-  FullHouse,
-  FourOfAKind,
-  FiveOfAKind,
-}
+    pub fn cards(&self) -> &str {
+        &self.cards
+    }
 
-fn convert_card_to_value(card: &char) -> u32 {
-    match card {
-        'A' => 15,
-        'K' => 14,
-        'Q' => 13,
-        'J' => 12,
-        'T' => 11,
-        '9' => 9,
-        '8' => 8,
-        '7' => 7,
-        '6' => 6,
-        '5' => 5,
-        '4' => 4,
-        '3' => 3,
-        '2' => 2,
-        _ => panic!("Unexpected value!"),
+    pub fn bet(&self) -> u32 {
+        self.bet
+    }
+
+    pub fn kind(&self, ruleset: &impl Ruleset) -> HandType {
+        hand_type(&self.cards, ruleset)
+    }
+
+    /// The hand's rank under the standard rules, worked out once when it's parsed.
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    pub fn key_with(&self, ruleset: &impl Ruleset) -> u64 {
+        sort_key(&self.cards, ruleset)
     }
 }
 
+// Hands rank by their key, and the cards and bet only break ties so the order agrees with `Eq`.
+impl Ord for Hand {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.key, &self.cards, self.bet).cmp(&(other.key, &other.cards, other.bet))
+    }
+}
 
 impl PartialOrd for Hand {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        if self.kind > other.kind {
-            return Some(std::cmp::Ordering::Greater);
-        }
-        if self.kind < other.kind {
-            return Some(std::cmp::Ordering::Less);
-        }
+        Some(self.cmp(other))
+    }
+}
 
-        for (c1, c2) in self.card.chars().zip(other.card.chars()) {
-            if c1 == c2 {
-                continue;
-            }
+/// Packs a hand's type and then each of its cards into one integer, a byte each, so comparing
+/// keys compares hands. Only meant for five card hands: with more than seven cards the type is
+/// shifted out of the key. Cards the ruleset doesn't know count as 0.
+pub fn sort_key(cards: &str, ruleset: &impl Ruleset) -> u64 {
+    cards
+        .chars()
+        .fold(hand_type(cards, ruleset) as u64, |key, card| {
+            let value = ruleset.card_value(card).unwrap_or(0).min(u8::MAX.into());
+            (key << 8) | u64::from(value)
+        })
+}
 
-            let v1 = convert_card_to_value(&c1);
-            let v2 = convert_card_to_value(&c2);
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HandType {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    FullHouse,
+    FourOfAKind,
+    FiveOfAKind,
+}
 
-            if v1 > v2 {
-                return Some(std::cmp::Ordering::Greater);
-            }
-            if v1 < v2 {
-                return Some(std::cmp::Ordering::Less);
-            }
-        }
-        Some(std::cmp::Ordering::Equal)
+/// How cards rank against each other and which of them stand in for any other card.
+pub trait Ruleset {
+    /// The strength of a card on its own, or `None` when it isn't part of the deck. Sort keys
+    /// only have room for values up to 255.
+    fn card_value(&self, card: char) -> Option<u32>;
+
+    fn is_wildcard(&self, _card: char) -> bool {
+        false
     }
 }
 
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        if self.kind > other.kind {
-            return std::cmp::Ordering::Greater;
-        }
-        if self.kind < other.kind {
-            return std::cmp::Ordering::Less;
-        }
-        for (c1, c2) in self.card.chars().zip(other.card.chars()) {
-            if c1 == c2 {
-                continue;
-            }
-
-            let v1 = convert_card_to_value(&c1);
-            let v2 = convert_card_to_value(&c2);
-
-            if v1 > v2 {
-                return std::cmp::Ordering::Greater;
-            }
-            if v1 < v2 {
-                return std::cmp::Ordering::Less;
-            }
-        }
-        std::cmp::Ordering::Equal
+/// The first half of the puzzle: `J` is a jack, ranked between `Q` and `T`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Standard;
+
+impl Ruleset for Standard {
+    fn card_value(&self, card: char) -> Option<u32> {
+        convert_card_to_value(&card)
     }
 }
 
-fn hand_type(hand: &HashMap<char, u32>) -> HandType {
-    let mut seen_two = false;
-    let mut seen_three = false;
+/// The second half of the puzzle: `J` is a joker that makes the best hand it can, but is the
+/// weakest card on its own.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Jokers;
 
-    for value in hand.values() {
-        match value {
-            5 => return HandType::FiveOfAKind,
-            4 => return HandType::FourOfAKind,
-            2 if seen_two => return HandType::TwoPair,
-            2 => seen_two = true,
-            3 => seen_three = true,
-            _ => (),
-        };
+impl Ruleset for Jokers {
+    fn card_value(&self, card: char) -> Option<u32> {
+        match card {
+            'J' => Some(1),
+            card => convert_card_to_value(&card),
+        }
     }
 
-    if seen_two && seen_three {
-        return HandType::FullHouse;
+    fn is_wildcard(&self, card: char) -> bool {
+        card == 'J'
     }
+}
+
+fn convert_card_to_value(card: &char) -> Option<u32> {
+    match card {
+        'A' => Some(15),
+        'K' => Some(14),
+        'Q' => Some(13),
+        'J' => Some(12),
+        'T' => Some(11),
+        '9' => Some(9),
+        '8' => Some(8),
+        '7' => Some(7),
+        '6' => Some(6),
+        '5' => Some(5),
+        '4' => Some(4),
+        '3' => Some(3),
+        '2' => Some(2),
+        _ => None,
+    }
+}
 
-    if seen_three {
-        return HandType::ThreeOfAKind;
+fn hand_type(cards: &str, ruleset: &impl Ruleset) -> HandType {
+    let mut hand = HashMap::new();
+    let mut wildcards = 0;
+    for card in cards.chars() {
+        if ruleset.is_wildcard(card) {
+            wildcards += 1;
+        } else {
+            *hand.entry(card).or_insert(0) += 1;
+        }
     }
 
-    if seen_two {
-        return HandType::OnePair;
+    let mut counts: Vec<u32> = hand.into_values().collect();
+    counts.sort_unstable_by(|a, b| b.cmp(a));
+    // Wildcards always do best by joining whichever card there's already the most of.
+    match counts.first_mut() {
+        Some(most) => *most += wildcards,
+        None => counts.push(wildcards),
     }
 
-    HandType::HighCard
+    match counts[..] {
+        [5, ..] => HandType::FiveOfAKind,
+        [4, ..] => HandType::FourOfAKind,
+        [3, 2, ..] => HandType::FullHouse,
+        [3, ..] => HandType::ThreeOfAKind,
+        [2, 2, ..] => HandType::TwoPair,
+        [2, ..] => HandType::OnePair,
+        _ => HandType::HighCard,
+    }
 }
 
-pub fn total_winnings(input: &str) -> u32 {
-    let mut  hands: Vec<Hand> = Vec::new();
-    for line in input.lines() {
-        let mut split = line.split_whitespace();
-        let cards = split.next().unwrap();
-        let points = split.next().unwrap();
-        let points = u32::from_str_radix(points, 10).unwrap();
+fn parse_hand(idx: usize, line: &str) -> Result<Hand, ParseError> {
+    let mut split = line.split_whitespace();
+    let (Some(cards), Some(points), None) = (split.next(), split.next(), split.next()) else {
+        return Err(ParseError::MissingBid {
+            location: Location::of(idx, line, line),
+            token: line.to_owned(),
+        });
+    };
+    if cards.chars().count() != 5 {
+        return Err(ParseError::WrongHandSize {
+            location: Location::of(idx, line, cards),
+            token: cards.to_owned(),
+        });
+    }
+    let points = points.parse::<u32>().map_err(|_| ParseError::InvalidBid {
+        location: Location::of(idx, line, points),
+        token: points.to_owned(),
+    })?;
 
-        let mut hand = HashMap::new();
-        for c in cards.chars() {
-            let val = hand.entry(c).or_insert(0);
-            *val += 1;
+    for (offset, c) in cards.char_indices() {
+        if convert_card_to_value(&c).is_none() {
+            return Err(ParseError::InvalidCard {
+                location: Location::of(idx, line, &cards[offset..]),
+                token: c.to_string(),
+            });
         }
-        let kind = hand_type(&hand);
-        hands.push(Hand::new(cards.to_owned(), kind, points));
     }
-    hands.sort();
+    Ok(Hand::new(cards.to_owned(), points))
+}
+
+pub struct Day7;
+
+impl Solution for Day7 {
+    type Input<'a> = Vec<Hand>;
+    type Answer = u64;
+    type Error = ParseError;
+
+    fn parse(input: &str) -> Result<Self::Input<'_>, Self::Error> {
+        input
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| parse_hand(idx, line))
+            .collect()
+    }
 
-    let mut winnings = 0;
-    let mut multiplier = 1;
+    fn part_one(input: &Self::Input<'_>) -> Result<Self::Answer, Self::Error> {
+        winnings_of_keys(input.iter().map(|hand| (hand.key, hand.bet)))
+    }
 
-    for hand in hands {
-        winnings += hand.bet * multiplier;
-        multiplier += 1;
+    fn part_two(input: &Self::Input<'_>) -> Option<Result<Self::Answer, Self::Error>> {
+        Some(winnings_of_hands(input, &Jokers))
     }
+}
 
-    winnings
+pub fn total_winnings(input: &str, ruleset: &impl Ruleset) -> Result<u64, ParseError> {
+    winnings_of_hands(&Day7::parse(input)?, ruleset)
+}
+
+fn winnings_of_hands(hands: &[Hand], ruleset: &impl Ruleset) -> Result<u64, ParseError> {
+    winnings_of_keys(hands.iter().map(|hand| (hand.key_with(ruleset), hand.bet)))
+}
+
+fn winnings_of_keys(hands: impl Iterator<Item = (u64, u32)>) -> Result<u64, ParseError> {
+    let mut hands: Vec<(u64, u32)> = hands.collect();
+    hands.sort_unstable_by_key(|&(key, _)| key);
+
+    let mut winnings: u64 = 0;
+
+    for (multiplier, (_, bet)) in (1_u64..).zip(hands) {
+        winnings = u64::from(bet)
+            .checked_mul(multiplier)
+            .and_then(|won| winnings.checked_add(won))
+            .ok_or(ParseError::Overflow("the total winnings"))?;
+    }
+
+    Ok(winnings)
 }
 
 #[cfg(test)]
@@ -167,13 +267,79 @@ mod tests {
 
     #[test]
     fn sample_total_winnings() {
-        let result = total_winnings(SAMPLE);
+        let result = total_winnings(SAMPLE, &Standard).unwrap();
         assert_eq!(result, 6440);
     }
 
     #[test]
     fn input_total_winnings() {
-        let result = total_winnings(INPUT);
+        let result = total_winnings(INPUT, &Standard).unwrap();
         assert_eq!(result, 251058093);
     }
+
+    #[test]
+    fn sample_total_winnings_with_jokers() {
+        let result = total_winnings(SAMPLE, &Jokers).unwrap();
+        assert_eq!(result, 5905);
+    }
+
+    #[test]
+    fn input_total_winnings_with_jokers() {
+        let result = total_winnings(INPUT, &Jokers).unwrap();
+        assert_eq!(result, 249781879);
+    }
+
+    #[test]
+    fn jokers_make_the_best_hand() {
+        assert_eq!(hand_type("KTJJT", &Standard), HandType::TwoPair);
+        assert_eq!(hand_type("KTJJT", &Jokers), HandType::FourOfAKind);
+        assert_eq!(hand_type("JJJJJ", &Jokers), HandType::FiveOfAKind);
+        assert_eq!(hand_type("2345J", &Jokers), HandType::OnePair);
+        assert_eq!(hand_type("2233J", &Jokers), HandType::FullHouse);
+        assert!(Jokers.card_value('J') < Jokers.card_value('2'));
+    }
+
+    #[test]
+    fn packed_keys() {
+        let hands = Day7::parse(SAMPLE).unwrap();
+        let mut ranked: Vec<&Hand> = hands.iter().collect();
+        ranked.sort_unstable_by_key(|hand| hand.key());
+        let ranked: Vec<&str> = ranked.iter().map(|hand| hand.cards()).collect();
+        assert_eq!(ranked, ["32T3K", "KTJJT", "KK677", "T55J5", "QQQJA"]);
+
+        let mut sorted = Day7::parse(INPUT).unwrap();
+        sorted.sort();
+        let mut by_key = Day7::parse(INPUT).unwrap();
+        by_key.sort_by_key(|hand| hand.key());
+        assert_eq!(sorted, by_key);
+
+        assert_eq!(sort_key("AAAAA", &Standard), 0x06_0f_0f_0f_0f_0f);
+        assert_eq!(sort_key("J2345", &Jokers), 0x01_01_02_03_04_05);
+        assert_eq!(hands[3].key_with(&Jokers), sort_key("KTJJT", &Jokers));
+        assert!(HandType::FullHouse > HandType::ThreeOfAKind);
+    }
+
+    #[test]
+    fn malformed_hands() {
+        let result = total_winnings("32T3K 765\nT55X5 684", &Standard);
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidCard {
+                location: Location::new(2, 4),
+                token: "X".to_owned(),
+            })
+        );
+
+        let result = total_winnings("32T3K", &Jokers);
+        assert_eq!(
+            result,
+            Err(ParseError::MissingBid {
+                location: Location::new(1, 1),
+                token: "32T3K".to_owned(),
+            })
+        );
+
+        let result = total_winnings("32T3K 4294967295\nKK677 3", &Standard);
+        assert_eq!(result, Ok(4294967295 + 3 * 2));
+    }
 }
@@ -0,0 +1,368 @@
+//! Regular poker, with suits, straights and flushes, ranked for the same bids as the camel cards.
+
+use crate::{winnings_of_keys, ParseError};
+use aoc_core::Location;
+use std::cmp::Reverse;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+impl Suit {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'c' => Some(Suit::Clubs),
+            'd' => Some(Suit::Diamonds),
+            'h' => Some(Suit::Hearts),
+            's' => Some(Suit::Spades),
+            _ => None,
+        }
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            Suit::Clubs => 'c',
+            Suit::Diamonds => 'd',
+            Suit::Hearts => 'h',
+            Suit::Spades => 's',
+        }
+    }
+}
+
+/// A card from a regular deck, written as its rank and then its suit, like `As` or `Td`. Ranks
+/// run from 2 up to 14 for the ace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Card {
+    pub rank: u8,
+    pub suit: Suit,
+}
+
+impl Card {
+    fn parse(text: &str) -> Option<Self> {
+        let mut chars = text.chars();
+        let (Some(rank), Some(suit), None) = (chars.next(), chars.next(), chars.next()) else {
+            return None;
+        };
+        let rank = match rank {
+            'A' => 14,
+            'K' => 13,
+            'Q' => 12,
+            'J' => 11,
+            'T' => 10,
+            '2'..='9' => rank.to_digit(10)? as u8,
+            _ => return None,
+        };
+        Some(Card {
+            rank,
+            suit: Suit::from_char(suit)?,
+        })
+    }
+}
+
+impl FromStr for Card {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Card::parse(s).ok_or_else(|| ParseError::InvalidCard {
+            location: Location::new(1, 1),
+            token: s.to_owned(),
+        })
+    }
+}
+
+impl Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rank = match self.rank {
+            14 => 'A',
+            13 => 'K',
+            12 => 'Q',
+            11 => 'J',
+            10 => 'T',
+            rank => char::from_digit(rank.into(), 10).unwrap_or('?'),
+        };
+        write!(f, "{rank}{}", self.suit.symbol())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+}
+
+/// How strong a five card hand is: its category, then the ranks that break ties in the order
+/// they're compared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PokerRank {
+    pub category: Category,
+    pub tiebreak: [u8; 5],
+}
+
+impl PokerRank {
+    /// Packs the rank into one integer like [`crate::sort_key`] does, four bits per card.
+    pub fn key(&self) -> u64 {
+        self.tiebreak
+            .iter()
+            .fold(self.category as u64, |key, &rank| {
+                (key << 4) | u64::from(rank)
+            })
+    }
+}
+
+/// Ranks five distinct cards.
+pub fn evaluate(hand: &[Card; 5]) -> PokerRank {
+    let mut counts = [0; 15];
+    for card in hand {
+        counts[usize::from(card.rank)] += 1;
+    }
+
+    // Biggest groups first, and the higher rank first between groups of the same size.
+    let mut groups: Vec<(usize, u8)> = (2..=14)
+        .rev()
+        .filter(|&rank| counts[usize::from(rank)] > 0)
+        .map(|rank| (counts[usize::from(rank)], rank))
+        .collect();
+    groups.sort_by_key(|&(count, _)| Reverse(count));
+
+    let mut tiebreak = [0; 5];
+    let ranks = groups
+        .iter()
+        .flat_map(|&(count, rank)| std::iter::repeat_n(rank, count));
+    for (slot, rank) in tiebreak.iter_mut().zip(ranks) {
+        *slot = rank;
+    }
+
+    let flush = hand.iter().all(|card| card.suit == hand[0].suit);
+    let straight = match tiebreak {
+        _ if groups.len() < 5 => None,
+        // The ace plays low in the wheel, so it's the weakest straight.
+        [14, 5, 4, 3, 2] => Some([5, 4, 3, 2, 1]),
+        [high, .., low] if high - low == 4 => Some(tiebreak),
+        _ => None,
+    };
+
+    let sizes: Vec<usize> = groups.iter().map(|&(count, _)| count).collect();
+    let category = match (straight, flush, &sizes[..]) {
+        (Some(_), true, _) => Category::StraightFlush,
+        (_, _, [4, ..]) => Category::FourOfAKind,
+        (_, _, [3, 2]) => Category::FullHouse,
+        (_, true, _) => Category::Flush,
+        (Some(_), _, _) => Category::Straight,
+        (_, _, [3, ..]) => Category::ThreeOfAKind,
+        (_, _, [2, 2, ..]) => Category::TwoPair,
+        (_, _, [2, ..]) => Category::OnePair,
+        _ => Category::HighCard,
+    };
+
+    PokerRank {
+        category,
+        tiebreak: straight.unwrap_or(tiebreak),
+    }
+}
+
+/// The best five card hand out of a handful of cards, like the seven of hold'em, or `None` when
+/// there are fewer than five. Every combination of five is tried, so this is meant for handfuls
+/// rather than whole decks.
+pub fn best_of(cards: &[Card]) -> Option<PokerRank> {
+    let n = cards.len();
+    if n < 5 {
+        return None;
+    }
+    let mut picks = [0, 1, 2, 3, 4];
+    let mut best = evaluate(&picks.map(|i| cards[i]));
+    // Step through the positions in lexicographic order: bump the last pick that still has room
+    // and put the ones after it right behind it.
+    while let Some(slot) = (0..5).rev().find(|&slot| picks[slot] < n - 5 + slot) {
+        picks[slot] += 1;
+        for next in slot + 1..5 {
+            picks[next] = picks[next - 1] + 1;
+        }
+        best = best.max(evaluate(&picks.map(|i| cards[i])));
+    }
+    Some(best)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PokerHand {
+    cards: Vec<Card>,
+    bet: u32,
+    rank: PokerRank,
+}
+
+impl PokerHand {
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    pub fn bet(&self) -> u32 {
+        self.bet
+    }
+
+    /// The best five cards in the hand, worked out once when it's parsed.
+    pub fn rank(&self) -> PokerRank {
+        self.rank
+    }
+}
+
+fn parse_poker_hand(idx: usize, line: &str) -> Result<PokerHand, ParseError> {
+    let mut tokens: Vec<&str> = line.split_whitespace().collect();
+    let Some(bet) = tokens.pop().filter(|_| !tokens.is_empty()) else {
+        return Err(ParseError::MissingBid {
+            location: Location::of(idx, line, line),
+            token: line.to_owned(),
+        });
+    };
+
+    // Cards can be run together (`AsKd`) or spaced out (`As Kd`).
+    let mut cards = Vec::new();
+    for token in &tokens {
+        let mut rest = *token;
+        while !rest.is_empty() {
+            let end = rest.char_indices().nth(2).map_or(rest.len(), |(i, _)| i);
+            let (text, tail) = rest.split_at(end);
+            let card = Card::parse(text).ok_or_else(|| ParseError::InvalidCard {
+                location: Location::of(idx, line, text),
+                token: text.to_owned(),
+            })?;
+            if cards.contains(&card) {
+                return Err(ParseError::DuplicateCard {
+                    location: Location::of(idx, line, text),
+                    token: text.to_owned(),
+                });
+            }
+            cards.push(card);
+            rest = tail;
+        }
+    }
+    if !(5..=7).contains(&cards.len()) {
+        return Err(ParseError::WrongDealSize {
+            location: Location::of(idx, line, tokens[0]),
+            token: tokens.join(" "),
+        });
+    }
+
+    let bet = bet.parse::<u32>().map_err(|_| ParseError::InvalidBid {
+        location: Location::of(idx, line, bet),
+        token: bet.to_owned(),
+    })?;
+    let rank = best_of(&cards).expect("at least five cards were dealt");
+    Ok(PokerHand { cards, bet, rank })
+}
+
+/// Parses one hand of five to seven suited cards and its bid per line.
+pub fn parse_poker_hands(input: &str) -> Result<Vec<PokerHand>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| parse_poker_hand(idx, line))
+        .collect()
+}
+
+/// The camel cards puzzle played with poker hands: every bid times the rank of its hand.
+pub fn poker_winnings(input: &str) -> Result<u64, ParseError> {
+    let hands = parse_poker_hands(input)?;
+    winnings_of_keys(hands.iter().map(|hand| (hand.rank.key(), hand.bet)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rank(cards: &str) -> PokerRank {
+        let cards: Vec<Card> = cards.split(' ').map(|card| card.parse().unwrap()).collect();
+        best_of(&cards).unwrap()
+    }
+
+    #[test]
+    fn categories() {
+        assert_eq!(rank("Ah Kh Qh Jh Th").category, Category::StraightFlush);
+        assert_eq!(rank("9c 9d 9h 9s 2c").category, Category::FourOfAKind);
+        assert_eq!(rank("3c 3d 3h Ks Kc").category, Category::FullHouse);
+        assert_eq!(rank("2d 7d 9d Jd Ad").category, Category::Flush);
+        assert_eq!(rank("6c 7d 8h 9s Tc").category, Category::Straight);
+        assert_eq!(rank("Qc Qd Qh 4s 7c").category, Category::ThreeOfAKind);
+        assert_eq!(rank("Jc Jd 4h 4s Ac").category, Category::TwoPair);
+        assert_eq!(rank("Tc Td 4h 5s Ac").category, Category::OnePair);
+        assert_eq!(rank("2c 5d 9h Js Kc").category, Category::HighCard);
+        assert_eq!(rank("Kc Ad Qh Js Tc").tiebreak, [14, 13, 12, 11, 10]);
+    }
+
+    #[test]
+    fn straights_and_kickers() {
+        let wheel = rank("Ac 2d 3h 4s 5c");
+        assert_eq!(wheel.category, Category::Straight);
+        assert!(wheel < rank("2c 3d 4h 5s 6c"));
+        assert_eq!(rank("Qc Kd Ah 2s 3c").category, Category::HighCard);
+
+        assert!(rank("Ac Ad Kh 5s 3c") > rank("As Ah Qc 5d 3h"));
+        assert!(rank("Ac Ad 5h 5s 3c") > rank("Kc Kd Qh Qs Ac"));
+        assert!(rank("Ac Ad 5h 5s 3c").key() > rank("Kc Kd Qh Qs Ac").key());
+        assert_eq!(rank("3c 3d 3h Ks Kc").tiebreak, [3, 3, 3, 13, 13]);
+        assert_eq!(rank("Ac Ad Kh 5s 3c").key(), 0x1_e_e_d_5_3);
+    }
+
+    #[test]
+    fn best_of_seven() {
+        let flop = rank("As Ks Qs Js Ts 2d 3c");
+        assert_eq!(flop.category, Category::StraightFlush);
+        assert_eq!(flop.tiebreak, [14, 13, 12, 11, 10]);
+
+        let full = rank("7c 7d 7h 2s 2c 2d Ah");
+        assert_eq!(full.category, Category::FullHouse);
+        assert_eq!(full.tiebreak, [7, 7, 7, 2, 2]);
+
+        assert_eq!(best_of(&[]), None);
+
+        // Past seven cards it's still every combination of five, just more of them.
+        let many = rank("2c 3d 4h 5s 7c 8d 9h Jc Qd Kh Ad 2s Ts");
+        assert_eq!(many.category, Category::Straight);
+        assert_eq!(many.tiebreak, [14, 13, 12, 11, 10]);
+    }
+
+    #[test]
+    fn poker_bids() {
+        let input = "AsKsQsJsTs 10\n2c 3d 4h 5s 7c 20\n7c7d7h2s2c2dAh 30\nAcAd5h5s3c 40";
+        let hands = parse_poker_hands(input).unwrap();
+        assert_eq!(hands[1].cards()[4].to_string(), "7c");
+        assert_eq!(hands[2].cards().len(), 7);
+        assert_eq!(poker_winnings(input), Ok(20 + 40 * 2 + 30 * 3 + 10 * 4));
+    }
+
+    #[test]
+    fn malformed_poker_hands() {
+        assert_eq!(
+            poker_winnings("AsKsQsJsTs 10\nAsKx 2d 3c 4h 20"),
+            Err(ParseError::InvalidCard {
+                location: Location::new(2, 3),
+                token: "Kx".to_owned(),
+            })
+        );
+        assert_eq!(
+            poker_winnings("AsKsQs Js As 10"),
+            Err(ParseError::DuplicateCard {
+                location: Location::new(1, 11),
+                token: "As".to_owned(),
+            })
+        );
+        assert_eq!(
+            poker_winnings("AsKs Qs 10"),
+            Err(ParseError::WrongDealSize {
+                location: Location::new(1, 1),
+                token: "AsKs Qs".to_owned(),
+            })
+        );
+    }
+}
@@ -1,112 +1,390 @@
-use std::collections::{HashMap};
+use aoc_core::{Location, Solution};
 use rayon::prelude::*;
+use std::collections::HashMap;
 
-pub fn desert_map(input: &str) -> u32 {
-    let mut traversal: HashMap<String, (String, String)> = HashMap::new();
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("the map doesn't start with a line of directions")]
+    MissingDirections,
+    #[error("{location}: unexpected direction `{token}`")]
+    InvalidDirection { location: Location, token: String },
+    #[error("{location}: expected `<node> = (<left>, <right>)` but found `{token}`")]
+    MalformedNode { location: Location, token: String },
+    #[error("{location}: node `{token}` is never defined")]
+    UnknownNode { location: Location, token: String },
+    #[error("the map doesn't have a `{0}` node")]
+    MissingNode(String),
+}
 
-    let mut lines = input.lines();
-    let directions = lines.next();
-    let mut directions = directions
-        .expect("Directions are missing")
-        .chars()
-        .into_iter()
-        .cycle();
+/// Why a map couldn't be solved: either it didn't parse, or the walk never ends.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("`ZZZ` can't be reached from `AAA`")]
+    NoPath,
+    #[error("the ghosts never all stand on a `Z` node at the same time")]
+    Unreachable,
+}
 
-    for line in lines {
-        if line.contains("=") {
-            let mut split = line.split(" = ");
-            let current_node = split.next().unwrap().to_owned();
-            let next_nodes = split.next().unwrap();
-            let mut split = next_nodes.split(", ");
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+}
 
-            let left = split.next().unwrap();
-            let right = split.next().unwrap();
+/// The nodes of a map interned to dense ids, with each node's left and right turn stored side by
+/// side. Names borrow from the input the network was parsed from.
+#[derive(Debug, Default)]
+pub struct Network<'a> {
+    names: Vec<&'a str>,
+    ids: HashMap<&'a str, u32>,
+    left: Vec<u32>,
+    right: Vec<u32>,
+    reverse: Vec<Vec<u32>>,
+}
 
-            let left = left.replace("(", "");
-            let right = right.replace(")", "");
-            traversal.insert(current_node, (left, right));
-        }
+impl<'a> Network<'a> {
+    fn intern(&mut self, name: &'a str) -> u32 {
+        *self.ids.entry(name).or_insert_with(|| {
+            self.names.push(name);
+            self.left.push(0);
+            self.right.push(0);
+            self.reverse.push(Vec::new());
+            (self.names.len() - 1) as u32
+        })
     }
-    let mut current_node = "AAA";
-    let mut steps = 0;
-    while current_node != "ZZZ" {
-        if let Some(next_nodes) = traversal.get(current_node) {
-            if let Some(direction) = directions.next() {
-                match direction {
-                    'L' => {
-                        current_node = &next_nodes.0;
-                    }
-                    'R' => {
-                        current_node = &next_nodes.1;
-                    }
-                    _ => panic!("Unexpected direction!"),
-                }
+
+    fn connect(&mut self, node: u32, left: u32, right: u32) {
+        self.left[node as usize] = left;
+        self.right[node as usize] = right;
+    }
+
+    // Only once every edge is known, since a node can be redefined further down the map.
+    fn link_reverse(&mut self) {
+        for node in self.nodes() {
+            let (left, right) = self.neighbors(node);
+            self.reverse[left as usize].push(node);
+            if right != left {
+                self.reverse[right as usize].push(node);
             }
         }
-        steps += 1;
     }
 
-    steps
-}
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
 
-pub fn desert_map_part_two(input: &'static str) -> u32 {
-    let mut traversal: HashMap<&'static str, (String, String)> = HashMap::new();
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
 
-    let mut lines = input.lines();
-    let directions = lines.next();
-    let mut directions = directions
-        .expect("Directions are missing")
-        .chars()
-        .into_iter()
-        .cycle();
+    pub fn id(&self, name: &str) -> Option<u32> {
+        self.ids.get(name).copied()
+    }
 
-    let mut starting_nodes: Vec<&str> = Vec::new();
+    pub fn name(&self, node: u32) -> &'a str {
+        self.names[node as usize]
+    }
 
-    for line in lines {
-        if line.contains("=") {
-            let mut split = line.split(" = ");
-            let current_node = split.next().unwrap();
-            if current_node.ends_with("A") {
-                starting_nodes.push(current_node);
-            }
+    pub fn nodes(&self) -> impl Iterator<Item = u32> {
+        0..self.names.len() as u32
+    }
 
-            let next_nodes = split.next().unwrap();
-            let mut split = next_nodes.split(", ");
+    /// The nodes reached by turning left and right.
+    pub fn neighbors(&self, node: u32) -> (u32, u32) {
+        (self.left[node as usize], self.right[node as usize])
+    }
 
-            let left = split.next().unwrap();
-            let right = split.next().unwrap();
+    /// The nodes with an edge into `node`.
+    pub fn predecessors(&self, node: u32) -> &[u32] {
+        &self.reverse[node as usize]
+    }
 
-            let left = left.replace("(", "");
-            let right = right.replace(")", "");
-            traversal.insert(current_node, (left, right));
+    pub fn step(&self, node: u32, direction: Direction) -> u32 {
+        match direction {
+            Direction::Left => self.left[node as usize],
+            Direction::Right => self.right[node as usize],
         }
     }
 
-    let mut steps = 0;
-    loop {
-        if let Some(direction) = directions.next() {
-            starting_nodes.par_iter_mut().for_each(|current_node| {
-                if let Some(next_nodes) = traversal.get(current_node) {
-                    match direction {
-                        'L' => {
-                            *current_node = &next_nodes.0;
-                        }
-                        'R' => {
-                            *current_node = &next_nodes.1;
-                        }
-                        _ => panic!("Unexpected direction!"),
-                    }
-                }
+    /// Follows the directions from `start` forever, yielding every node after each step.
+    pub fn walk<'n>(
+        &'n self,
+        start: u32,
+        directions: &'n [Direction],
+    ) -> impl Iterator<Item = u32> + 'n {
+        directions
+            .iter()
+            .cycle()
+            .scan(start, move |node, &direction| {
+                *node = self.step(*node, direction);
+                Some(*node)
+            })
+    }
+}
+
+#[derive(Debug)]
+pub struct DesertMap<'a> {
+    directions: Vec<Direction>,
+    network: Network<'a>,
+}
+
+impl<'a> DesertMap<'a> {
+    pub fn directions(&self) -> &[Direction] {
+        &self.directions
+    }
+
+    pub fn network(&self) -> &Network<'a> {
+        &self.network
+    }
+}
+
+fn parse_directions(line: &str) -> Result<Vec<Direction>, ParseError> {
+    line.char_indices()
+        .map(|(offset, c)| match c {
+            'L' => Ok(Direction::Left),
+            'R' => Ok(Direction::Right),
+            _ => Err(ParseError::InvalidDirection {
+                location: Location::of(0, line, &line[offset..]),
+                token: c.to_string(),
+            }),
+        })
+        .collect()
+}
+
+fn parse_node(idx: usize, line: &str) -> Result<(&str, (&str, &str)), ParseError> {
+    let node = line
+        .split_once(" = ")
+        .and_then(|(current_node, next_nodes)| {
+            let next_nodes = next_nodes.strip_prefix('(')?.strip_suffix(')')?;
+            let (left, right) = next_nodes.split_once(", ")?;
+            Some((current_node, (left, right)))
+        });
+    node.ok_or_else(|| ParseError::MalformedNode {
+        location: Location::of(idx, line, line),
+        token: line.to_owned(),
+    })
+}
+
+pub struct Day8;
+
+impl Solution for Day8 {
+    type Input<'a> = DesertMap<'a>;
+    type Answer = u64;
+    type Error = Error;
+
+    fn parse(input: &str) -> Result<Self::Input<'_>, Self::Error> {
+        let mut network = Network::default();
+        let mut edges = Vec::new();
+
+        let mut lines = input.lines().enumerate();
+        let (_, directions) = lines.next().ok_or(ParseError::MissingDirections)?;
+        let directions = parse_directions(directions)?;
+        if directions.is_empty() {
+            return Err(ParseError::MissingDirections.into());
+        }
+
+        for (idx, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (current_node, (left, right)) = parse_node(idx, line)?;
+            edges.push((idx, line, network.intern(current_node), left, right));
+        }
+
+        // Every node is defined by now, so anything that doesn't resolve was never defined.
+        for (idx, line, node, left, right) in edges {
+            let [left, right] = [left, right].map(|next| {
+                network.id(next).ok_or_else(|| ParseError::UnknownNode {
+                    location: Location::of(idx, line, next),
+                    token: next.to_owned(),
+                })
             });
+            network.connect(node, left?, right?);
         }
-        steps += 1;
+        network.link_reverse();
+
+        Ok(DesertMap {
+            directions,
+            network,
+        })
+    }
+
+    fn part_one(input: &Self::Input<'_>) -> Result<Self::Answer, Self::Error> {
+        steps_to_zzz(input)
+    }
+
+    fn part_two(input: &Self::Input<'_>) -> Option<Result<Self::Answer, Self::Error>> {
+        Some(ghost_steps_to_z(input))
+    }
+}
+
+pub fn desert_map(input: &str) -> Result<u64, Error> {
+    steps_to_zzz(&Day8::parse(input)?)
+}
 
-        if starting_nodes.par_iter_mut().all(|node| node.ends_with("Z")) {
-            break;
+pub fn desert_map_part_two(input: &str) -> Result<u64, Error> {
+    ghost_steps_to_z(&Day8::parse(input)?)
+}
+
+fn steps_to_zzz(map: &DesertMap) -> Result<u64, Error> {
+    let [start, end] = ["AAA", "ZZZ"].map(|node| {
+        map.network
+            .id(node)
+            .ok_or_else(|| ParseError::MissingNode(node.to_owned()))
+    });
+    let (start, end) = (start?, end?);
+
+    // Past `nodes * directions` steps the walk is only going round in circles.
+    let limit = map.network.len() * map.directions.len();
+    map.network
+        .walk(start, &map.directions)
+        .take(limit)
+        .position(|node| node == end)
+        .map(|steps| steps as u64 + 1)
+        .ok_or(Error::NoPath)
+}
+
+// Where a ghost stands over time: it wanders for `offset` steps and then repeats every `period`
+// steps. `exits` are the steps before its first repeat at which it stands on a `Z` node.
+#[derive(Debug, PartialEq, Eq)]
+struct Cycle {
+    offset: u64,
+    period: u64,
+    exits: Vec<u64>,
+}
+
+impl Cycle {
+    fn is_exit(&self, step: u64) -> bool {
+        let step = match step.checked_sub(self.offset) {
+            Some(since) if step >= self.offset + self.period => self.offset + since % self.period,
+            _ => step,
+        };
+        self.exits.binary_search(&step).is_ok()
+    }
+}
+
+// A ghost's state is its node together with where it is in the directions, so it has to repeat
+// within `nodes * directions` steps.
+fn find_cycle(map: &DesertMap, start: u32) -> Cycle {
+    let width = map.directions.len();
+    let mut seen = vec![None; map.network.len() * width];
+    let mut exits = Vec::new();
+    let mut node = start;
+    let mut step = 0;
+    loop {
+        let position = (step % width as u64) as usize;
+        let state = node as usize * width + position;
+        if let Some(offset) = seen[state] {
+            return Cycle {
+                offset,
+                period: step - offset,
+                exits,
+            };
+        }
+        seen[state] = Some(step);
+        if map.network.name(node).ends_with('Z') {
+            exits.push(step);
         }
+
+        node = map.network.step(node, map.directions[position]);
+        step += 1;
     }
+}
 
-    steps
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - a / b * y)
+    }
+}
+
+// Chinese remainder theorem for moduli that don't have to be coprime.
+fn combine((a1, m1): (i128, i128), (a2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let (g, p, _) = extended_gcd(m1, m2);
+    if (a2 - a1) % g != 0 {
+        return None;
+    }
+    let lcm = m1 / g * m2;
+    let k = ((a2 - a1) / g * p).rem_euclid(m2 / g);
+    Some(((a1 + m1 * k).rem_euclid(lcm), lcm))
+}
+
+fn ghost_steps_to_z(map: &DesertMap) -> Result<u64, Error> {
+    let starting_nodes: Vec<u32> = map
+        .network
+        .nodes()
+        .filter(|&node| map.network.name(node).ends_with('A'))
+        .collect();
+    let cycles: Vec<Cycle> = starting_nodes
+        .par_iter()
+        .map(|&node| find_cycle(map, node))
+        .collect();
+    if cycles.is_empty() {
+        return Err(Error::Unreachable);
+    }
+
+    // The usual shape: every ghost reaches its only exit exactly one period in, and already
+    // inside its loop, so they all line up at the lcm of the periods.
+    if cycles
+        .iter()
+        .all(|cycle| cycle.offset <= cycle.period && cycle.exits == [cycle.period])
+    {
+        return cycles
+            .iter()
+            .try_fold(1u64, |lcm, cycle| {
+                (lcm / gcd(lcm, cycle.period)).checked_mul(cycle.period)
+            })
+            .ok_or(Error::Unreachable);
+    }
+
+    // Until every ghost has settled into its loop, check the steps one at a time.
+    let settled = cycles.iter().map(|cycle| cycle.offset).max().unwrap_or(0);
+    if let Some(step) = (1..settled).find(|&step| cycles.iter().all(|c| c.is_exit(step))) {
+        return Ok(step);
+    }
+
+    // After that, each exit inside a ghost's loop comes back around modulo its period.
+    let mut candidates = vec![(0, 1)];
+    for cycle in &cycles {
+        let period = cycle.period as i128;
+        let residues: Vec<i128> = cycle
+            .exits
+            .iter()
+            .filter(|&&exit| exit >= cycle.offset)
+            .map(|&exit| exit as i128 % period)
+            .collect();
+        candidates = candidates
+            .iter()
+            .flat_map(|&candidate| {
+                residues
+                    .iter()
+                    .filter_map(move |&residue| combine(candidate, (residue, period)))
+            })
+            .collect();
+    }
+
+    let lower = settled.max(1) as i128;
+    candidates
+        .into_iter()
+        .map(|(residue, modulus)| {
+            let laps = (lower - residue).max(0) + modulus - 1;
+            residue + laps / modulus * modulus
+        })
+        .min()
+        .and_then(|step| u64::try_from(step).ok())
+        .ok_or(Error::Unreachable)
 }
 
 #[cfg(test)]
@@ -120,28 +398,123 @@ mod tests {
 
     #[test]
     fn sample_desert_map() {
-        let result = desert_map(SAMPLE);
+        let result = desert_map(SAMPLE).unwrap();
         assert_eq!(result, 2);
 
-        let result = desert_map(SAMPLE2);
+        let result = desert_map(SAMPLE2).unwrap();
         assert_eq!(result, 6);
     }
 
     #[test]
     fn sample_desert_map_part_two() {
-        let result = desert_map_part_two(SAMPLE_PART_TWO);
+        let result = desert_map_part_two(SAMPLE_PART_TWO).unwrap();
         assert_eq!(result, 6);
     }
 
     #[test]
     fn input_desert_map() {
-        let result = desert_map(INPUT);
+        let result = desert_map(INPUT).unwrap();
         assert_eq!(result, 13939);
     }
 
     #[test]
     fn input_desert_map_part_two() {
-        let result = desert_map_part_two(INPUT);
-        assert_eq!(result, 21);
+        let result = desert_map_part_two(INPUT).unwrap();
+        assert_eq!(result, 8906539031197);
+    }
+
+    #[test]
+    fn ghost_cycles() {
+        let map = Day8::parse(SAMPLE_PART_TWO).unwrap();
+        assert_eq!(
+            find_cycle(&map, map.network.id("11A").unwrap()),
+            Cycle {
+                offset: 1,
+                period: 2,
+                exits: vec![2],
+            }
+        );
+        assert_eq!(
+            find_cycle(&map, map.network.id("22A").unwrap()),
+            Cycle {
+                offset: 1,
+                period: 6,
+                exits: vec![3, 6],
+            }
+        );
+
+        // 11A sits on its exit from the first step on, 22A only gets there on the third.
+        let map = Day8::parse(
+            "L\n\n11A = (11Z, 11A)\n11Z = (11Z, 11Z)\n\
+             22A = (22B, 22A)\n22B = (22C, 22A)\n22C = (22Z, 22A)\n22Z = (22B, 22A)",
+        )
+        .unwrap();
+        assert_eq!(ghost_steps_to_z(&map), Ok(3));
+
+        let map = Day8::parse(
+            "L\n\n11A = (11Z, 11A)\n11Z = (11A, 11A)\n\
+             22A = (22B, 22A)\n22B = (22Z, 22A)\n22Z = (22B, 22A)",
+        )
+        .unwrap();
+        assert_eq!(ghost_steps_to_z(&map), Err(Error::Unreachable));
+
+        // 11A's only exit is one period in, but before its loop starts, so it never comes back.
+        let map = Day8::parse(
+            "L\n\n11A = (11B, 11B)\n11B = (11C, 11C)\n11C = (11Z, 11Z)\n11Z = (11D, 11D)\n\
+             11D = (11E, 11E)\n11E = (11F, 11F)\n11F = (11D, 11D)\n\
+             22A = (22B, 22B)\n22B = (22Z, 22Z)\n22Z = (22B, 22B)",
+        )
+        .unwrap();
+        assert_eq!(ghost_steps_to_z(&map), Err(Error::Unreachable));
+    }
+
+    #[test]
+    fn network_edges() {
+        let map = Day8::parse(SAMPLE).unwrap();
+        let network = map.network();
+        assert_eq!(network.len(), 7);
+        let id = |name| network.id(name).unwrap();
+
+        assert_eq!(network.neighbors(id("AAA")), (id("BBB"), id("CCC")));
+        assert_eq!(network.step(id("CCC"), Direction::Left), id("ZZZ"));
+        assert_eq!(network.predecessors(id("ZZZ")), [id("CCC"), id("ZZZ")]);
+        assert_eq!(network.predecessors(id("AAA")), []);
+
+        let walked: Vec<&str> = network
+            .walk(id("AAA"), map.directions())
+            .take(3)
+            .map(|node| network.name(node))
+            .collect();
+        assert_eq!(walked, ["CCC", "ZZZ", "ZZZ"]);
+    }
+
+    #[test]
+    fn malformed_map() {
+        let result = desert_map("LR\n\nAAA = (BBB, ZZZ)\nZZZ = (ZZZ, ZZZ)");
+        assert_eq!(
+            result,
+            Err(Error::Parse(ParseError::UnknownNode {
+                location: Location::new(3, 8),
+                token: "BBB".to_owned(),
+            }))
+        );
+
+        let result = desert_map_part_two("LRX\n\nAAA = (AAA, AAA)");
+        assert_eq!(
+            result,
+            Err(Error::Parse(ParseError::InvalidDirection {
+                location: Location::new(1, 3),
+                token: "X".to_owned(),
+            }))
+        );
+
+        let result = desert_map("L\n\nAAA = (BBB, BBB)\nBBB = (AAA, AAA)\nZZZ = (ZZZ, ZZZ)");
+        assert_eq!(result, Err(Error::NoPath));
+
+        let result = desert_map("L\n\nAAA = (AAA, AAA)");
+        assert_eq!(
+            result,
+            Err(Error::Parse(ParseError::MissingNode("ZZZ".to_owned())))
+        );
     }
 }
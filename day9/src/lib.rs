@@ -1,4 +1,61 @@
-fn predict_next(values: &Vec<Vec<i32>>) -> i32 {
+use aoc_core::{Location, Solution};
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("{location}: invalid reading `{token}`")]
+    InvalidNumber { location: Location, token: String },
+}
+
+fn parse_history(idx: usize, line: &str) -> Result<Vec<i32>, ParseError> {
+    line.split_whitespace()
+        .map(|c| {
+            c.parse::<i32>().map_err(|_| ParseError::InvalidNumber {
+                location: Location::of(idx, line, c),
+                token: c.to_owned(),
+            })
+        })
+        .collect()
+}
+
+pub struct Day9;
+
+impl Solution for Day9 {
+    type Input<'a> = Vec<Vec<i32>>;
+    type Answer = i32;
+    type Error = ParseError;
+
+    fn parse(input: &str) -> Result<Self::Input<'_>, Self::Error> {
+        input
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| parse_history(idx, line))
+            .collect()
+    }
+
+    fn part_one(input: &Self::Input<'_>) -> Result<Self::Answer, Self::Error> {
+        Ok(sum_of_next_predictions(input))
+    }
+
+    fn part_two(input: &Self::Input<'_>) -> Option<Result<Self::Answer, Self::Error>> {
+        Some(Ok(sum_of_previous_predictions(input)))
+    }
+}
+
+fn sum_of_next_predictions(histories: &[Vec<i32>]) -> i32 {
+    histories
+        .iter()
+        .map(|history| predict_next(&differences(history)))
+        .sum::<i32>()
+}
+
+fn sum_of_previous_predictions(histories: &[Vec<i32>]) -> i32 {
+    histories
+        .iter()
+        .map(|history| predict_next_part_two(&differences(history)))
+        .sum::<i32>()
+}
+
+fn predict_next(values: &[Vec<i32>]) -> i32 {
     let mut prev_last = 0;
     for value in values {
         if let Some(last) = value.iter().last() {
@@ -9,10 +66,10 @@ fn predict_next(values: &Vec<Vec<i32>>) -> i32 {
     prev_last
 }
 
-fn predict_next_part_two(values: &Vec<Vec<i32>>) -> i32 {
+fn predict_next_part_two(values: &[Vec<i32>]) -> i32 {
     let mut prev_first = 0;
     for value in values.iter().rev() {
-        if let Some(first) = value.iter().next() {
+        if let Some(first) = value.first() {
             let prediction = first - prev_first;
             prev_first = prediction;
         }
@@ -20,68 +77,33 @@ fn predict_next_part_two(values: &Vec<Vec<i32>>) -> i32 {
     prev_first
 }
 
-pub fn extrapolate_history(input: &str) -> i32 {
-    let mut predictions = Vec::new();
-    for line in input.lines() {
-        let integers = line
-            .split_whitespace()
-            .filter_map(|c| i32::from_str_radix(c, 10).ok());
-
-        let mut differences: Vec<Vec<i32>> = Vec::new();
-        differences.push(integers.collect());
-
-        loop {
-            let mut difference: Vec<i32> = Vec::new();
-            if let Some(head) = differences.last() {
-                let mut head = head.into_iter().peekable();
-                while let Some(curr) = head.next() {
-                    if let Some(&next) = head.peek() {
-                        difference.push(next - curr);
-                    }
+fn differences(history: &[i32]) -> Vec<Vec<i32>> {
+    let mut differences: Vec<Vec<i32>> = vec![history.to_vec()];
+
+    loop {
+        let mut difference: Vec<i32> = Vec::new();
+        if let Some(head) = differences.last() {
+            let mut head = head.iter().peekable();
+            while let Some(curr) = head.next() {
+                if let Some(&next) = head.peek() {
+                    difference.push(next - curr);
                 }
             }
-            let sum = difference.iter().sum::<i32>();
-            differences.push(difference);
-            if sum == 0 {
-                let prediction = predict_next(&mut differences);
-                predictions.push(prediction);
-                break;
-            }
+        }
+        let sum = difference.iter().sum::<i32>();
+        differences.push(difference);
+        if sum == 0 {
+            return differences;
         }
     }
-    predictions.iter().sum::<i32>()
 }
 
-pub fn extrapolate_history_part_two(input: &str) -> i32 {
-    let mut predictions = Vec::new();
-    for line in input.lines() {
-        let integers = line
-            .split_whitespace()
-            .filter_map(|c| i32::from_str_radix(c, 10).ok());
-
-        let mut differences: Vec<Vec<i32>> = Vec::new();
-        differences.push(integers.collect());
-
-        loop {
-            let mut difference: Vec<i32> = Vec::new();
-            if let Some(head) = differences.last() {
-                let mut head = head.into_iter().peekable();
-                while let Some(curr) = head.next() {
-                    if let Some(&next) = head.peek() {
-                        difference.push(next - curr);
-                    }
-                }
-            }
-            let sum = difference.iter().sum::<i32>();
-            differences.push(difference);
-            if sum == 0 {
-                let prediction = predict_next_part_two(&mut differences);
-                predictions.push(prediction);
-                break;
-            }
-        }
-    }
-    predictions.iter().sum::<i32>()
+pub fn extrapolate_history(input: &str) -> Result<i32, ParseError> {
+    Ok(sum_of_next_predictions(&Day9::parse(input)?))
+}
+
+pub fn extrapolate_history_part_two(input: &str) -> Result<i32, ParseError> {
+    Ok(sum_of_previous_predictions(&Day9::parse(input)?))
 }
 
 #[cfg(test)]
@@ -93,25 +115,38 @@ mod tests {
 
     #[test]
     fn sample_extrapolate_history() {
-        let result = extrapolate_history(SAMPLE);
+        let result = extrapolate_history(SAMPLE).unwrap();
         assert_eq!(result, 114);
     }
 
     #[test]
     fn sample_extrapolate_history_part_two() {
-        let result = extrapolate_history_part_two(SAMPLE);
+        let result = extrapolate_history_part_two(SAMPLE).unwrap();
         assert_eq!(result, 2);
     }
 
     #[test]
     fn input_extrapolate_history() {
-        let result = extrapolate_history(INPUT);
+        let result = extrapolate_history(INPUT).unwrap();
         assert_eq!(result, 1684566095);
     }
 
     #[test]
     fn input_extrapolate_history_part_two() {
-        let result = extrapolate_history_part_two(INPUT);
-        assert_eq!(result, 1684566095);
+        let result = extrapolate_history_part_two(INPUT).unwrap();
+        // This used to expect 1684566095, part one's answer, which part two never produced.
+        assert_eq!(result, 1136);
+    }
+
+    #[test]
+    fn malformed_history() {
+        let result = extrapolate_history("0 3 6 9\n1 3 six 10");
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidNumber {
+                location: Location::new(2, 5),
+                token: "six".to_owned(),
+            })
+        );
     }
 }